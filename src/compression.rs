@@ -0,0 +1,142 @@
+// the gzip and zstd files are decompressed while they are read and compressed again when they are
+// written, the format is found from the first bytes of the file or from its extension
+use flate2::Compression as GzipLevel;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+pub enum Writer {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+impl Compression {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gzip" | "gz" => Some(Compression::Gzip),
+            "zstd" | "zst" => Some(Compression::Zstd),
+            "none" => Some(Compression::None),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    // the compression given by the extension of the path (ex: data.csv.gz)
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    // the compression given by the first bytes of the file, its extension when it is empty
+    pub fn detect(path: &str) -> io::Result<Self> {
+        let mut magic: Vec<u8> = Vec::new();
+        File::open(path)?.take(4).read_to_end(&mut magic)?;
+        Ok(if magic.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if magic.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if magic.is_empty() {
+            Compression::from_path(path)
+        } else {
+            Compression::None
+        })
+    }
+}
+
+// opens the file for reading, a compressed file is decompressed while it is read
+pub fn open(path: &str) -> io::Result<Box<dyn Read>> {
+    let compression = Compression::detect(path)?;
+    let file = File::open(path)?;
+    Ok(match compression {
+        Compression::None => Box::new(file),
+        // a gzip file can be made of several members (ex: cat a.gz b.gz)
+        Compression::Gzip => Box::new(MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(file)?),
+    })
+}
+
+impl Writer {
+    pub fn new(file: File, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => Writer::Plain(file),
+            Compression::Gzip => Writer::Gzip(GzEncoder::new(file, GzipLevel::default())),
+            Compression::Zstd => Writer::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    // writes the end of the compressed data, returns the file so it can be synced
+    pub fn finish(self) -> io::Result<File> {
+        match self {
+            Writer::Plain(file) => Ok(file),
+            Writer::Gzip(encoder) => encoder.finish(),
+            Writer::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Writer::Plain(file) => file.write(buf),
+            Writer::Gzip(encoder) => encoder.write(buf),
+            Writer::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Writer::Plain(file) => file.flush(),
+            Writer::Gzip(encoder) => encoder.flush(),
+            Writer::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn compress_and_decompress() {
+        for compression in [Compression::Gzip, Compression::Zstd, Compression::None] {
+            // the extension does not tell the format, the first bytes do
+            let name = format!("csvparser_{}_{}.csv", std::process::id(), compression.name());
+            let path = std::env::temp_dir().join(name);
+            let path = path.to_string_lossy().to_string();
+            let mut writer = Writer::new(File::create(&path).unwrap(), compression).unwrap();
+            writer.write_all(b"id,name\n1,bob\n").unwrap();
+            writer.finish().unwrap();
+            assert_eq!(Compression::detect(&path).unwrap(), compression);
+            let mut content = String::new();
+            open(&path).unwrap().read_to_string(&mut content).unwrap();
+            assert_eq!(content, "id,name\n1,bob\n");
+            let _ = fs::remove_file(path);
+        }
+        assert_eq!(Compression::from_path("a.csv.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("a.csv"), Compression::None);
+    }
+}
@@ -3,87 +3,421 @@ use crate::log_info;
 use crate::log_warning;
 use crate::{OPTIONS, Options};
 use super::query_engine;
+use crate::compression::{self, Compression, Writer};
+use crate::editor::{Line, LineEditor};
+use crate::encoding::{self, DecodingReader};
+use crate::sampling::{Reservoir, Rng, SampleSize};
+use crate::schema::{self, Schema, Validator};
+use crate::session::{Session, Table};
+use crate::sniffer;
+use crate::statements::Statements;
+use encoding_rs::{Encoding, UTF_8};
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Write, stdin, stdout},
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write, stdin, stdout},
+    path::{Path, PathBuf},
+    process,
 };
+// the separator used when the file does not tell which one it uses
+pub const DEFAULT_SEPARATOR: &str = ",";
+
+// how the values of a csv file are separated, every loaded table keeps its own dialect
+#[derive(Debug, Clone)]
+pub struct Dialect {
+    pub separator: String,
+    // a value that starts with the quote can hold the separator, None when the values are not quoted
+    pub quote: Option<char>,
+    pub on_bad_row: BadRowPolicy,
+    // the first line of the file holds the names of the fields
+    pub has_header: bool,
+    // the names given by --header, they replace the header of the file or name the fields of a
+    // file without header
+    pub names: Option<Vec<String>>,
+    // the lines before the header, they are kept and written back unchanged
+    pub skip_lines: usize,
+    // the separator an exported copy is written with, the separator the file was read with when
+    // None, a saved file keeps its separator so the skipped and kept raw lines still match it
+    pub output_separator: Option<String>,
+    pub encoding: &'static Encoding,
+    // the bytes that are not valid in the encoding are replaced instead of failing the load
+    pub lossy: bool,
+    // the file starts with a byte order mark, it is written back
+    pub byte_order_mark: bool,
+    // the lines end with \r\n, they are written back with it
+    pub crlf: bool,
+    // the file is compressed again in this format when it is written
+    pub compression: Compression,
+}
+
+// the content of a csv file
+pub struct CsvFile {
+    pub fields: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub bad_rows: Vec<BadRow>,
+    // the lines skipped by --skip-lines
+    pub preamble: Vec<String>,
+}
+
+// what is done with a line that does not have as many values as the header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BadRowPolicy {
+    Skip,
+    // the file is not loaded
+    Fail,
+    // the missing values are empty, the lines with too many values are skipped
+    Pad,
+    // the line is kept out of the table and written back unchanged when the table is saved
+    KeepRaw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BadRowAction {
+    Skipped,
+    Padded,
+    KeptRaw,
+}
+
+#[derive(Debug, Clone)]
+pub struct BadRow {
+    pub line: usize,
+    pub raw: String,
+    pub values: usize,
+    // the number of rows read before the line, a kept line is written back before that row, it is
+    // not updated when the rows are deleted, inserted or sorted
+    pub position: usize,
+    pub action: BadRowAction,
+}
+
+impl BadRowPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(BadRowPolicy::Skip),
+            "fail" => Some(BadRowPolicy::Fail),
+            "pad" => Some(BadRowPolicy::Pad),
+            "keep-raw" => Some(BadRowPolicy::KeepRaw),
+            _ => None,
+        }
+    }
+}
+
+impl Dialect {
+    // the dialect given by the command line options
+    pub fn from_options(options: &HashMap<Options, String>) -> Self {
+        let on_bad_row = options
+            .get(&Options::OnBadRow)
+            .and_then(|policy| BadRowPolicy::from_name(policy))
+            .unwrap_or(BadRowPolicy::Skip);
+        let quote = match options.get(&Options::Quote).map(|quote| quote.as_str()) {
+            Some("none") => None,
+            Some(quote) => quote.chars().next(),
+            None => Some('"'),
+        };
+        Dialect {
+            separator: options
+                .get(&Options::FieldsSeparator)
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_SEPARATOR.to_string()),
+            quote,
+            on_bad_row,
+            has_header: true,
+            names: None,
+            skip_lines: 0,
+            output_separator: None,
+            encoding: options
+                .get(&Options::Encoding)
+                .and_then(|name| encoding::from_name(name))
+                .unwrap_or(UTF_8),
+            lossy: options.contains_key(&Options::Lossy),
+            byte_order_mark: false,
+            crlf: false,
+            compression: Compression::None,
+        }
+    }
+
+    pub fn write_separator(&self) -> &str {
+        self.output_separator.as_deref().unwrap_or(&self.separator)
+    }
+
+    // the dialect of a file, the separator given by open or by the options is kept, the settings
+    // that are not given are guessed from the start of the file
+    pub fn of_file(path: &str, separator: Option<&str>) -> Self {
+        let options = OPTIONS.get().unwrap();
+        Dialect::from_options(options).guess(path, separator, None, options)
+    }
+
+    // the dialect of the file given on the command line, the header options only describe that
+    // file, the other files are read with a header when it looks like they have one
+    pub fn for_main_file(path: &str, options: &HashMap<Options, String>) -> Self {
+        // --header replaces the header of the file unless --no-header tells there is none
+        let has_header = if options.contains_key(&Options::NoHeader) {
+            Some(false)
+        } else if options.contains_key(&Options::HasHeader) || options.contains_key(&Options::Header)
+        {
+            Some(true)
+        } else {
+            None
+        };
+        let dialect = Dialect {
+            has_header: has_header.unwrap_or(true),
+            names: options
+                .get(&Options::Header)
+                .map(|names| names.split(',').map(|name| name.trim().to_string()).collect()),
+            skip_lines: options
+                .get(&Options::SkipLines)
+                .and_then(|lines| lines.parse().ok())
+                .unwrap_or(0),
+            ..Dialect::from_options(options)
+        };
+        dialect.guess(path, None, has_header, options)
+    }
+
+    // the sniffer guesses the settings that were not given by the user, has_header is given by the
+    // header options
+    fn guess(
+        mut self,
+        path: &str,
+        separator: Option<&str>,
+        has_header: Option<bool>,
+        options: &HashMap<Options, String>,
+    ) -> Self {
+        self.compression = Compression::detect(path).unwrap_or(Compression::None);
+        let Some(sample) = sniffer::sample(path, self.skip_lines, self.encoding) else {
+            // the error is reported when the file is loaded
+            return self;
+        };
+        if let Some(encoding) = sample.byte_order_mark {
+            self.encoding = encoding;
+            self.byte_order_mark = true;
+        }
+        self.crlf = sample.crlf;
+        let lines = sample.lines;
+        let separator = separator.or(options.get(&Options::FieldsSeparator).map(|s| s.as_str()));
+        let quote = options.get(&Options::Quote).map(|_| self.quote);
+        let guess = sniffer::sniff(&lines, separator, quote);
+        self.separator = guess.separator;
+        self.quote = guess.quote;
+        self.has_header = has_header.unwrap_or(guess.has_header);
+        let separator = match self.separator.as_str() {
+            "\t" => "tab".to_string(),
+            separator => format!("'{}'", separator.escape_debug()),
+        };
+        let quote = match self.quote {
+            Some(quote) => format!("the quote {quote}"),
+            None => "no quote".to_string(),
+        };
+        let header = match self.has_header {
+            true => "a header",
+            false => "no header",
+        };
+        let byte_order_mark = match self.byte_order_mark {
+            true => " with a byte order mark",
+            false => "",
+        };
+        let compression = match self.compression {
+            Compression::None => String::new(),
+            compression => format!("{}, ", compression.name()),
+        };
+        log_info!(
+            "{} is read with the separator {}, {} and {} ({}{}{}, {} line endings)",
+            path,
+            separator,
+            quote,
+            header,
+            compression,
+            self.encoding.name(),
+            byte_order_mark,
+            if self.crlf { "CRLF" } else { "LF" }
+        );
+        self
+    }
+}
+
 pub fn run(path: &str) {
     // getting the content of the file
-    let (mut fields, mut rows): (Vec<String>, Vec<Vec<String>>);
-    match parse_file(path) {
-        Some((f, r)) => {
-            fields = f;
-            rows = r;
-        }
+    let options = OPTIONS.get().unwrap();
+    let schema = match options.get(&Options::Schema) {
+        Some(schema_path) => match Schema::load(schema_path) {
+            Some(schema) => Some(schema),
+            None => return,
+        },
+        None => None,
+    };
+    let alias = Table::alias_from_path(path);
+    let table = match Table::load(path, &alias, Dialect::for_main_file(path, options), schema) {
+        Some(table) => table,
         None => return,
-    }
+    };
+    let mut session = Session::new(table);
+    let mut editor = LineEditor::new();
+    let mut statements = Statements::default();
 
     // main loop
-    loop {
-        let mut command = String::new();
-        println!("command: ");
-        match stdin().read_line(&mut command) {
-            Ok(_) => {
-                if command.trim_end() == "quit" {
-                    print!("do you want to save the changes? (y or n): ");
-                    let _ = stdout().flush();
-                    command.clear();
-                    // reading the user choice
-                    match stdin().read_line(&mut command) {
-                        Ok(_) => match command.trim_end() {
-                            // the user want to save changes
-                            "y" => {
-                                // will open and clear the file that is already exist
-                                let file = File::create(path);
-                                match file {
-                                    Ok(mut f) => {
-                                        // write the fields names
-                                        if let Err(e) = writeln!(f, "{}", fields.join(",")) {
-                                            log_error!("Failed to write to file: {}", e);
-                                            continue;
-                                        }
-                                        // write the rest of the rows
-                                        for line in rows.iter() {
-                                            if let Err(e) = writeln!(f, "{}", line.join(",")) {
-                                                log_error!("Failed to write to file: {}", e);
-                                                continue;
-                                            }
-                                        }
-                                        log_info!("the changes has been written to: {}", path);
-                                        return;
-                                    }
-                                    Err(e) => {
-                                        log_error!("Failed to create file: {}", e);
-                                        continue;
-                                    }
-                                }
-                            }
-                            // the user don't want to save changes
-                            "n" => {
-                                return;
-                            }
-                            _ => {
-                                println!("{}", command);
-                                continue;
-                            }
-                        },
-                        Err(_) => {
-                            log_error!("failed to read your choice");
+    'main: loop {
+        let mut prompt = prompt(&session);
+        if statements.is_open() {
+            // the continuation prompt is aligned on the prompt
+            prompt = format!("{:>width$}", "...> ", width = prompt.len());
+        }
+        match editor.read_line(&prompt, &session.current().fields) {
+            // the end of the input is handled as a quit command, nothing more can be read so the
+            // program ends even if a table could not be saved
+            Line::End => {
+                quit(&mut session);
+                break;
+            }
+            Line::Interrupted => statements.clear(),
+            Line::Command(line) => {
+                for statement in statements.push_line(&line) {
+                    if statement == "quit" {
+                        if quit(&mut session) {
+                            break 'main;
                         }
+                        continue;
                     }
+                    execute(&statement, &mut session);
+                    println!();
                 }
-                query_engine::query(command.trim_end().to_string(), &mut fields, &mut rows);
-                println!();
             }
+        }
+    }
+    editor.save_history();
+}
+
+// the name of the current file, its rows count and a * with the number of unsaved changes
+// (ex: employees.csv 120 rows *2> )
+fn prompt(session: &Session) -> String {
+    let table = session.current();
+    let unsaved = match table.unsaved_changes() {
+        0 => String::new(),
+        changes => format!(" *{changes}"),
+    };
+    format!("{} {} rows{}> ", table.file_name(), table.rows.len(), unsaved)
+}
+
+// runs a command or a query, returns false if it failed
+pub fn execute(command: &str, session: &mut Session) -> bool {
+    if let Some(path) = command.strip_prefix("source ") {
+        session.enter_script();
+        let succeeded = source(path.trim(), session);
+        session.leave_script();
+        return succeeded;
+    }
+    if let Some(succeeded) = session.command(command) {
+        return succeeded;
+    }
+    match query_engine::meta_command(command, session) {
+        Some(succeeded) => succeeded,
+        None => query_engine::query(command.to_string(), session),
+    }
+}
+
+// runs the commands of a script file as one batch, when a command fails the changes made by the
+// script are rolled back and the remaining commands are not run
+fn source(path: &str, session: &mut Session) -> bool {
+    let Some(path) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        log_error!("usage: source \"script.txt\"");
+        return false;
+    };
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => {
+            log_error!("can not open the file {path}");
+            return false;
+        }
+    };
+    let checkpoint = session.checkpoint();
+    let mut statements = Statements::default();
+    // the line where the current statement starts, used in the errors
+    let mut start = 0;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
             Err(_) => {
-                log_error!("failed to read your command, please try again");
+                log_error!("an error accord while reading the file {path}");
+                session.restore(checkpoint);
+                return false;
+            }
+        };
+        if !statements.is_open() {
+            // the comments are skipped, a # in a statement that is not finished is kept
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            start = i + 1;
+        }
+        for command in statements.push_line(&line) {
+            if !execute(&command, session) {
+                log_error!(
+                    "the line {} of {} failed, the changes of the script are rolled back",
+                    start,
+                    path
+                );
+                session.restore(checkpoint);
+                return false;
             }
         }
     }
+    if statements.is_open() {
+        log_error!(
+            "the statement at the line {} of {} is not finished, the changes of the script are rolled back",
+            start,
+            path
+        );
+        session.restore(checkpoint);
+        return false;
+    }
+    true
+}
+
+// asks what to do with an open transaction then offers to save every table that has unsaved
+// changes, returns false if the user cancels or a table could not be saved
+fn quit(session: &mut Session) -> bool {
+    while session.in_transaction() {
+        let question = "the transaction was not committed, commit, roll back or cancel? (c, r or x): ";
+        match ask(question).as_deref() {
+            Some("c") => session.commit(),
+            // the end of the input can not answer, the transaction is rolled back
+            Some("r") | None => session.rollback(None),
+            Some("x") => return false,
+            Some(_) => continue,
+        };
+    }
+    let mut saved = true;
+    for table in session.tables_mut() {
+        saved &= table.offer_to_save();
+    }
+    if !saved {
+        log_error!("some changes were not saved, quit again to leave");
+    }
+    saved
 }
-pub fn parse_file(path: &str) -> Option<(Vec<String>, Vec<Vec<String>>)> {
-    let file_result = File::open(path);
-    let file: File;
+
+// prints the question and returns the answer of the user, None if it could not be read
+pub fn ask(question: &str) -> Option<String> {
+    print!("{question}");
+    let _ = stdout().flush();
+    let mut answer = String::new();
+    match stdin().read_line(&mut answer) {
+        Ok(0) => None,
+        Ok(_) => Some(answer.trim_end().to_string()),
+        Err(_) => {
+            log_error!("failed to read your choice");
+            None
+        }
+    }
+}
+// the rows that break the schema are reported with their line number, they are loaded anyway, the
+// lines that do not have as many values as the header are handled by the bad rows policy and
+// returned with the rows
+pub fn parse_file(
+    path: &str,
+    dialect: &Dialect,
+    schema: Option<&Schema>,
+    options: &HashMap<Options, String>,
+) -> Option<CsvFile> {
+    let file_result = compression::open(path);
+    let file: Box<dyn Read>;
     match file_result {
         Ok(val) => file = val,
         Err(_) => {
@@ -91,14 +425,18 @@ pub fn parse_file(path: &str) -> Option<(Vec<String>, Vec<Vec<String>>)> {
             return None;
         }
     }
-    // determining the separator
-    let separator: String;
-    let options = OPTIONS.get().unwrap();
-    match options.get(&Options::FieldsSeparator) {
-        Some(sep) => separator = sep.clone(),
-        None => unreachable!("the default fields separator is not set"),
-    }
-    let reader = BufReader::new(file);
+    let separator = &dialect.separator;
+    // with the --sample option only the sampled rows are kept in memory while reading the file
+    let sample_size: Option<SampleSize> = options
+        .get(&Options::Sample)
+        .and_then(|size| SampleSize::parse(size));
+    let seed: Option<u64> = options.get(&Options::Seed).and_then(|seed| seed.parse().ok());
+    let mut reservoir: Option<Reservoir<Vec<String>>> = match sample_size {
+        Some(SampleSize::Count(count)) => Some(Reservoir::new(count, seed)),
+        _ => None,
+    };
+    let mut rng = Rng::new(seed);
+    let mut reader = BufReader::new(DecodingReader::new(file, dialect.encoding, dialect.lossy));
 
     let mut get_fields = true;
 
@@ -106,54 +444,568 @@ pub fn parse_file(path: &str) -> Option<(Vec<String>, Vec<Vec<String>>)> {
     let mut fields: Vec<String> = Vec::new();
 
     let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut validator: Option<Validator> = None;
+    let mut bad_rows: Vec<BadRow> = Vec::new();
+    let mut preamble: Vec<String> = Vec::new();
+    // the number of rows read, the sampled out rows included
+    let mut read = 0;
     log_info!("loading the csv file...");
-    for line in reader.lines() {
+    for (i, line) in (&mut reader).lines().enumerate() {
         let mut line_content = String::new();
         match line {
             Ok(content) => line_content.push_str(&content),
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                log_error!(
+                    "the line {} of {} is not valid {}, use --encoding (ex: --encoding=latin1) or --lossy",
+                    i + 1,
+                    path,
+                    dialect.encoding.name()
+                );
+                return None;
+            }
             Err(_) => {
                 log_error!("an error accord while loading the file {path}");
                 return None;
             }
         }
+        if i < dialect.skip_lines {
+            preamble.push(line_content);
+            continue;
+        }
         if line_content.is_empty() {
             continue;
         }
         // if the fields are not extracted yet
         if get_fields {
             get_fields = false;
-            let fields_vals: Vec<&str> = line_content.split(separator.as_str()).collect();
-            for val in fields_vals {
-                let field = val.trim().to_string();
-                if field.is_empty() {
-                    log_error!("the name of a field should not be empty");
+            let fields_vals = split_line(&line_content, separator, dialect.quote);
+            fields = field_names(dialect, &fields_vals)?;
+            validator = schema.map(|schema| schema.validator(&fields));
+            // without header the first line is a row
+            if dialect.has_header {
+                continue;
+            }
+        }
+
+        let mut line_vals = split_line(&line_content, separator, dialect.quote);
+        if line_vals.len() != fields.len() {
+            let action = match dialect.on_bad_row {
+                BadRowPolicy::Fail => {
+                    log_error!(
+                        "the line {} contains {} values, but there are {} fields: {}",
+                        i + 1,
+                        line_vals.len(),
+                        fields.len(),
+                        line_content
+                    );
                     return None;
                 }
-                fields.push(val.trim().to_string());
+                BadRowPolicy::Pad if line_vals.len() < fields.len() => BadRowAction::Padded,
+                BadRowPolicy::KeepRaw => BadRowAction::KeptRaw,
+                BadRowPolicy::Skip | BadRowPolicy::Pad => BadRowAction::Skipped,
+            };
+            bad_rows.push(BadRow {
+                line: i + 1,
+                raw: line_content.clone(),
+                values: line_vals.len(),
+                position: read,
+                action,
+            });
+            if action != BadRowAction::Padded {
+                continue;
             }
+            line_vals.resize(fields.len(), String::new());
+        }
+        read += 1;
+        let row = line_vals;
+        // so the empty lines will be escaped
+        if row.is_empty() {
             continue;
         }
+        if let Some(validator) = validator.as_mut() {
+            validator.check_row(i + 1, &row);
+        }
+        match (&mut reservoir, sample_size) {
+            (Some(reservoir), _) => reservoir.push(row),
+            (None, Some(SampleSize::Percent(percent))) => {
+                if rng.next_f64() * 100f64 < percent as f64 {
+                    rows.push(row);
+                }
+            }
+            _ => rows.push(row),
+        }
+    }
+    if let Some(reservoir) = reservoir {
+        rows = reservoir.into_vec();
+    }
+    if sample_size.is_some() {
+        log_info!("{} rows were sampled from the file", rows.len());
+    }
+    if reader.get_ref().replaced {
+        log_warning!(
+            "some bytes of {} are not valid {}, they are replaced by \u{FFFD}",
+            path,
+            dialect.encoding.name()
+        );
+    }
+    if !bad_rows.is_empty() {
+        log_warning!(
+            "{} lines do not have {} values, use bad-rows to see them",
+            bad_rows.len(),
+            fields.len()
+        );
+    }
+    if let Some(validator) = validator
+        && !validator.violations.is_empty()
+    {
+        log_warning!(
+            "{} does not follow its schema ({} violations)",
+            path,
+            validator.violations.len()
+        );
+        schema::report(&validator.violations);
+    }
+    Some(CsvFile {
+        fields,
+        rows,
+        bad_rows,
+        preamble,
+    })
+}
 
-        let line_vals: Vec<&str> = line_content.split(separator.as_str()).collect();
-        if line_vals.len() != fields.len() {
-            log_warning!(
-                "the line {} contains {} value, but there is {} field name",
-                line_content,
-                line_vals.len(),
-                fields.len()
+// the names of the fields from the first line of the file and the header options, a file without
+// header has its fields named c1, c2...
+fn field_names(dialect: &Dialect, first_line: &[String]) -> Option<Vec<String>> {
+    let names: Vec<String> = match (&dialect.names, dialect.has_header) {
+        (Some(names), has_header) if names.len() != first_line.len() => {
+            log_error!(
+                "--header gives {} names but the {} of the file has {} fields",
+                names.len(),
+                if has_header { "header" } else { "first line" },
+                first_line.len()
             );
-            log_warning!("the line will be ignored");
+            return None;
+        }
+        (Some(names), _) => names.clone(),
+        (None, true) => first_line.iter().map(|val| val.trim().to_string()).collect(),
+        (None, false) => (1..=first_line.len()).map(|i| format!("c{i}")).collect(),
+    };
+    if names.iter().any(|name| name.is_empty()) {
+        log_error!("the name of a field should not be empty");
+        return None;
+    }
+    Some(rename_duplicates(names))
+}
+
+// a repeated field name gets the suffix of its occurrence (ex: name, name_2), so every field can be
+// referenced
+fn rename_duplicates(names: Vec<String>) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    for name in names.iter() {
+        if !fields.contains(name) {
+            fields.push(name.clone());
             continue;
         }
-        let mut row: Vec<String> = Vec::new();
-        for i in 0..line_vals.len() {
-            let val = line_vals[i].trim().to_string();
-            row.push(val);
+        let mut n = 2;
+        // the suffixed name could be a field of the file too
+        while fields.contains(&format!("{name}_{n}")) || names.contains(&format!("{name}_{n}")) {
+            n += 1;
         }
-        // so the empty lines will be escaped
-        if !row.is_empty() {
-            rows.push(row);
+        let renamed = format!("{name}_{n}");
+        log_warning!(
+            "the field {} is repeated, it is renamed to {} (the file is saved with the new name)",
+            name,
+            renamed
+        );
+        fields.push(renamed);
+    }
+    fields
+}
+
+// a separator given by the user, \t, \\ and \xHH (ex: \x1f) are escapes, None if an escape is not
+// valid
+pub fn unescape(separator: &str) -> Option<String> {
+    let mut unescaped = String::new();
+    let mut chars = separator.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            't' => unescaped.push('\t'),
+            '\\' => unescaped.push('\\'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 && byte.is_ascii() => unescaped.push(byte as char),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        }
+    }
+    if unescaped.is_empty() {
+        None
+    } else {
+        Some(unescaped)
+    }
+}
+
+// splits a line into its values, the values are trimmed but a value between quotes is kept as it is
+// and can hold the separator and doubled quotes (ex: "say ""hi"", bob")
+pub fn split_line(line: &str, separator: &str, quote: Option<char>) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    let mut value = String::new();
+    // the value started with a quote
+    let mut quoted = false;
+    let mut in_quote = false;
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        let c = rest.chars().next().unwrap();
+        i += c.len_utf8();
+        if in_quote {
+            match Some(c) == quote {
+                // a doubled quote is a quote of the value
+                true if line[i..].starts_with(c) => {
+                    value.push(c);
+                    i += c.len_utf8();
+                }
+                true => in_quote = false,
+                false => value.push(c),
+            }
+        } else if !separator.is_empty() && rest.starts_with(separator) {
+            i += separator.len() - c.len_utf8();
+            let value = std::mem::take(&mut value);
+            values.push(if quoted { value } else { value.trim().to_string() });
+            quoted = false;
+        } else if Some(c) == quote && !quoted && value.trim().is_empty() {
+            value.clear();
+            quoted = true;
+            in_quote = true;
+        } else if !(quoted && c.is_whitespace()) {
+            value.push(c);
+        }
+    }
+    values.push(if quoted { value } else { value.trim().to_string() });
+    values
+}
+
+// joins the values of a row, the values that would not be read back the same are quoted, a value
+// that can not be read back is an error: a line break (the files are read one line at a time) or
+// the separator when the values are not quoted
+pub fn join_row(values: &[String], dialect: &Dialect) -> io::Result<String> {
+    let separator = dialect.write_separator();
+    let mut line: Vec<String> = Vec::with_capacity(values.len());
+    for value in values {
+        let reason = if value.contains(['\n', '\r']) {
+            "a line break"
+        } else if dialect.quote.is_none() && value.contains(separator) {
+            "the separator and --quote=none does not quote it"
+        } else {
+            ""
+        };
+        if !reason.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("the value {value:?} holds {reason}, it would not be read back"),
+            ));
+        }
+        line.push(match dialect.quote {
+            Some(quote)
+                if value.contains(separator)
+                    || value.starts_with(quote)
+                    || value.trim() != value =>
+            {
+                let doubled = value.replace(quote, &format!("{quote}{quote}"));
+                format!("{quote}{doubled}{quote}")
+            }
+            _ => value.clone(),
+        });
+    }
+    Ok(line.join(separator))
+}
+
+// writes the fields and rows to a temporary file next to path then renames it to path, so the file
+// is never left partly written, with the --backup option the previous file is kept as path.bak, the
+// preamble and the kept bad rows are written back unchanged at their place
+pub fn write_file(
+    path: &str,
+    dialect: &Dialect,
+    fields: &[String],
+    rows: &[Vec<String>],
+    bad_rows: &[BadRow],
+    preamble: &[String],
+    options: &HashMap<Options, String>,
+) -> bool {
+    let (file, tmp_path) = match create_temporary(path) {
+        Ok(created) => created,
+        Err(e) => {
+            log_error!("Failed to create file: {}", e);
+            return false;
         }
+    };
+    // the file is compressed in the format of the dialect
+    let written = Writer::new(file, dialect.compression).and_then(|writer| {
+        let mut writer = BufWriter::new(writer);
+        write_lines(&mut writer, dialect, fields, rows, bad_rows, preamble)?;
+        let writer = writer.into_inner().map_err(|e| e.into_error())?;
+        writer.finish()?.sync_all()
+    });
+    if let Err(e) = written {
+        log_error!("Failed to write to file: {}", e);
+        let _ = fs::remove_file(&tmp_path);
+        return false;
+    }
+    let backup = options.contains_key(&Options::Backup);
+    if backup
+        && Path::new(path).exists()
+        && let Err(e) = fs::copy(path, format!("{path}.bak"))
+    {
+        log_error!("Failed to back up {}: {}", path, e);
+        let _ = fs::remove_file(&tmp_path);
+        return false;
+    }
+    if let Err(e) = fs::rename(&tmp_path, path) {
+        log_error!("Failed to replace {}: {}", path, e);
+        let _ = fs::remove_file(&tmp_path);
+        return false;
+    }
+    true
+}
+
+// creates a new file next to path to write it, the name is taken by no other file so a file of the
+// user is never overwritten (ex: .data.csv.1234.0.tmp)
+fn create_temporary(path: &str) -> io::Result<(File, PathBuf)> {
+    let path = Path::new(path);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut n = 0;
+    loop {
+        let tmp_path = path.with_file_name(format!(".{name}.{}.{n}.tmp", process::id()));
+        match OpenOptions::new().write(true).create_new(true).open(&tmp_path) {
+            Ok(file) => return Ok((file, tmp_path)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// writes the preamble, the fields names then the rest of the rows in the encoding and with the line
+// endings of the file, a character the encoding can not represent fails the writing
+fn write_lines(
+    writer: &mut impl Write,
+    dialect: &Dialect,
+    fields: &[String],
+    rows: &[Vec<String>],
+    bad_rows: &[BadRow],
+    preamble: &[String],
+) -> io::Result<()> {
+    if dialect.byte_order_mark {
+        writer.write_all(encoding::byte_order_mark(dialect.encoding))?;
+    }
+    let line_ending = if dialect.crlf { "\r\n" } else { "\n" };
+    let mut write_line = |line: &str| {
+        let line = format!("{line}{line_ending}");
+        let bytes = encoding::encode(&line, dialect.encoding).map_err(|c| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "the character {c} can not be written in {}, choose an encoding that can \
+                     represent it with --encoding (ex: --encoding=utf-8)",
+                    dialect.encoding.name()
+                ),
+            )
+        })?;
+        writer.write_all(&bytes)
+    };
+    let mut kept = bad_rows
+        .iter()
+        .filter(|bad_row| bad_row.action == BadRowAction::KeptRaw)
+        .peekable();
+    for line in preamble {
+        write_line(line)?;
+    }
+    if dialect.has_header {
+        write_line(&join_row(fields, dialect)?)?;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        while let Some(bad_row) = kept.next_if(|bad_row| bad_row.position <= i) {
+            write_line(&bad_row.raw)?;
+        }
+        write_line(&join_row(row, dialect)?)?;
+    }
+    for bad_row in kept {
+        write_line(&bad_row.raw)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn dialect(on_bad_row: BadRowPolicy) -> Dialect {
+        Dialect {
+            on_bad_row,
+            ..Dialect::from_options(&HashMap::new())
+        }
+    }
+
+    fn read(path: &str, dialect: &Dialect) -> Option<CsvFile> {
+        parse_file(path, dialect, None, &HashMap::new())
+    }
+
+    // the name holds the process id so two runs of the tests do not write the same file
+    fn write_csv(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("csvparser_{}_{name}", process::id()));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    const CONTENT: &str = "id,name\n1,bob\n2\n3,ann,x\n4,zed\n";
+
+    #[test]
+    fn bad_rows_policies() {
+        let path = write_csv("bad_rows.csv", CONTENT);
+        let file = read(&path, &dialect(BadRowPolicy::Skip)).unwrap();
+        assert_eq!(file.rows.len(), 2);
+        assert_eq!(
+            file.bad_rows
+                .iter().map(|b| (b.line, b.values)).collect::<Vec<_>>(),
+            vec![(3, 1), (4, 3)]
+        );
+        let rows = read(&path, &dialect(BadRowPolicy::Pad))
+            .unwrap()
+            .rows;
+        assert_eq!(rows[1], vec!["2", ""]);
+        assert_eq!(rows.len(), 3);
+        assert!(read(&path, &dialect(BadRowPolicy::Fail)).is_none());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn kept_rows_are_written_back() {
+        let path = write_csv("keep_raw.csv", CONTENT);
+        let dialect = dialect(BadRowPolicy::KeepRaw);
+        let file = read(&path, &dialect).unwrap();
+        assert_eq!(file.rows.len(), 2);
+        assert!(write_file(
+            &path,
+            &dialect,
+            &file.fields,
+            &file.rows,
+            &file.bad_rows,
+            &file.preamble,
+            &HashMap::new()
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), CONTENT);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn escaped_separators() {
+        assert_eq!(unescape("\\t").as_deref(), Some("\t"));
+        assert_eq!(unescape("\\x1f").as_deref(), Some("\x1f"));
+        assert_eq!(unescape("||").as_deref(), Some("||"));
+        assert_eq!(unescape("a\\\\").as_deref(), Some("a\\"));
+        assert!(unescape("\\q").is_none());
+        assert!(unescape("\\x1").is_none());
+        assert_eq!(split_line("1||bob||", "||", Some('"')), vec!["1", "bob", ""]);
+    }
+
+    #[test]
+    fn quoted_values() {
+        let quote = Some('"');
+        assert_eq!(
+            split_line(r#"1, "bob, jr" ,"say ""hi""",x"#, ",", quote),
+            vec!["1", "bob, jr", "say \"hi\"", "x"]
+        );
+        assert_eq!(split_line("a;;\"b", ";", None), vec!["a", "", "\"b"]);
+        let dialect = dialect(BadRowPolicy::Skip);
+        let row = ["bob, jr", "say \"hi\"", " x"].map(|v| v.to_string());
+        let line = join_row(&row, &dialect).unwrap();
+        assert_eq!(line, r#""bob, jr",say "hi"," x""#);
+        assert_eq!(split_line(&line, ",", quote), row);
+        // the values that would not be read back are not written
+        assert!(join_row(&["a\nb".to_string()], &dialect).is_err());
+        let dialect = Dialect { quote: None, ..dialect };
+        assert!(join_row(&["a,b".to_string()], &dialect).is_err());
+        assert!(join_row(&["a;b".to_string()], &dialect).is_ok());
+    }
+
+    #[test]
+    fn repeated_field_names() {
+        let names = ["name", "name", "name_2", "age", "name"].map(|n| n.to_string()).to_vec();
+        assert_eq!(
+            rename_duplicates(names),
+            vec!["name", "name_3", "name_2", "age", "name_4"]
+        );
+    }
+
+    #[test]
+    fn header_options() {
+        let content = "exported by x\n\n1,bob\n2,ann\n";
+        let path = write_csv("no_header.csv", content);
+        let mut dialect = Dialect {
+            has_header: false,
+            skip_lines: 2,
+            ..dialect(BadRowPolicy::Skip)
+        };
+        let file = read(&path, &dialect).unwrap();
+        assert_eq!(file.fields, vec!["c1", "c2"]);
+        assert_eq!(file.rows.len(), 2);
+        assert_eq!(file.preamble, vec!["exported by x", ""]);
+        assert!(write_file(
+            &path,
+            &dialect,
+            &file.fields,
+            &file.rows,
+            &file.bad_rows,
+            &file.preamble,
+            &HashMap::new()
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        dialect.names = Some(vec!["id".to_string(), "name".to_string()]);
+        assert_eq!(read(&path, &dialect).unwrap().fields, vec!["id", "name"]);
+        // the names replace the header, the first line is not a row
+        dialect.has_header = true;
+        assert_eq!(read(&path, &dialect).unwrap().rows.len(), 1);
+        dialect.names = Some(vec!["id".to_string()]);
+        assert!(read(&path, &dialect).is_none());
+        // without header the names are counted against the first row
+        dialect.has_header = false;
+        assert!(read(&path, &dialect).is_none());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn header_given_over_the_guess() {
+        let path = write_csv("numbers_header.csv", "2019,2020\n1,2\n3,4\n");
+        assert!(!dialect(BadRowPolicy::Skip).guess(&path, None, None, &HashMap::new()).has_header);
+        let dialect = dialect(BadRowPolicy::Skip).guess(&path, None, Some(true), &HashMap::new());
+        let file = read(&path, &dialect).unwrap();
+        assert_eq!(file.fields, vec!["2019", "2020"]);
+        assert_eq!(file.rows.len(), 2);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn backup_option() {
+        let path = write_csv("backup.csv", "id\n1\n");
+        let dialect = dialect(BadRowPolicy::Skip);
+        let rows = vec![vec!["2".to_string()]];
+        let fields = vec!["id".to_string()];
+        assert!(write_file(&path, &dialect, &fields, &rows, &[], &[], &HashMap::new()));
+        assert!(!Path::new(&format!("{path}.bak")).exists());
+        let options = HashMap::from([(Options::Backup, String::new())]);
+        let rows = vec![vec!["3".to_string()]];
+        assert!(write_file(&path, &dialect, &fields, &rows, &[], &[], &options));
+        assert_eq!(fs::read_to_string(format!("{path}.bak")).unwrap(), "id\n2\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "id\n3\n");
+        let _ = fs::remove_file(format!("{path}.bak"));
+        let _ = fs::remove_file(path);
     }
-    Some((fields, rows))
 }
@@ -0,0 +1,179 @@
+// the line editor of the prompt: arrows, a history kept between the runs and the tab completion
+// of the keywords, the functions names and the fields of the current table
+use crate::log_warning;
+use crate::query_engine;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::PathBuf;
+
+pub struct Completion {
+    words: Vec<&'static str>,
+    // the fields of the current table, updated before every line is read
+    fields: Vec<String>,
+}
+
+impl Completion {
+    fn new() -> Self {
+        // the keywords hold the commands of the session, they are help topics
+        let mut words = query_engine::keywords();
+        words.sort_unstable();
+        words.dedup();
+        Completion {
+            words,
+            fields: Vec::new(),
+        }
+    }
+
+    // the candidates for the word at the end of the line, a $ completes the fields names
+    fn candidates(&self, word: &str) -> Vec<String> {
+        let prefix = word.strip_prefix("${").or_else(|| word.strip_prefix('$'));
+        match prefix {
+            Some(prefix) => self
+                .fields
+                .iter()
+                .filter(|field| field.starts_with(prefix))
+                .map(|field| query_engine::field_reference(field))
+                .collect(),
+            None => self
+                .words
+                .iter()
+                .filter(|keyword| keyword.starts_with(word))
+                .map(|keyword| keyword.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        // a field between braces that is not closed yet can hold spaces
+        let start = match line[..pos].rfind("${") {
+            Some(idx) if !line[idx..pos].contains('}') => idx,
+            _ => line[..pos].rfind([' ', '(']).map_or(0, |idx| idx + 1),
+        };
+        let word = &line[start..pos];
+        if word.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+        Ok((start, self.candidates(word)))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+pub struct LineEditor {
+    editor: Option<Editor<Completion, DefaultHistory>>,
+}
+
+// what was read from the user
+pub enum Line {
+    Command(String),
+    // ctrl-c, the current line is dropped
+    Interrupted,
+    // ctrl-d or the end of the input
+    End,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        let editor = match Editor::new() {
+            Ok(mut editor) => {
+                editor.set_helper(Some(Completion::new()));
+                if let Some(path) = history_path() {
+                    // there is no history file on the first run
+                    let _ = editor.load_history(&path);
+                }
+                Some(editor)
+            }
+            Err(_) => {
+                log_warning!("the line editor is not available, the history will not be kept");
+                None
+            }
+        };
+        LineEditor { editor }
+    }
+
+    pub fn read_line(&mut self, prompt: &str, fields: &[String]) -> Line {
+        let Some(editor) = self.editor.as_mut() else {
+            return read_stdin(prompt);
+        };
+        if let Some(completion) = editor.helper_mut() {
+            completion.fields = fields.to_vec();
+        }
+        match editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    let _ = editor.add_history_entry(line.as_str());
+                }
+                Line::Command(line)
+            }
+            Err(ReadlineError::Interrupted) => Line::Interrupted,
+            Err(_) => Line::End,
+        }
+    }
+
+    // writes the history to the history file of the user
+    pub fn save_history(&mut self) {
+        if let (Some(editor), Some(path)) = (self.editor.as_mut(), history_path())
+            && editor.save_history(&path).is_err()
+        {
+            log_warning!("can not write the history file {}", path.display());
+        }
+    }
+}
+
+// the history of the commands is kept in the home directory of the user
+fn history_path() -> Option<PathBuf> {
+    std::env::home_dir().map(|home| home.join(".csvparser_history"))
+}
+
+fn read_stdin(prompt: &str) -> Line {
+    use std::io::{Write, stdin, stdout};
+    print!("{prompt}");
+    let _ = stdout().flush();
+    let mut line = String::new();
+    match stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => Line::End,
+        Ok(_) => Line::Command(line.trim_end().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_keywords_and_fields() {
+        let mut completion = Completion::new();
+        completion.fields = vec!["name".to_string(), "age".to_string(), "nick".to_string()];
+        assert_eq!(completion.candidates("betw"), vec!["between"]);
+        assert_eq!(completion.candidates("distinct"), vec!["distinct", "distinct-on"]);
+        assert_eq!(completion.candidates("counte"), vec!["counter"]);
+        assert_eq!(completion.candidates("savep"), vec!["savepoint"]);
+        assert!(completion.candidates("sch").is_empty());
+        assert_eq!(completion.candidates("$n"), vec!["$name", "$nick"]);
+        assert!(completion.candidates("$x").is_empty());
+        completion.fields = vec!["Total Sales".to_string()];
+        assert_eq!(completion.candidates("${To"), vec!["${Total Sales}"]);
+    }
+}
@@ -0,0 +1,177 @@
+// the files are decoded to utf-8 when they are read and encoded back when they are written, the byte
+// order mark is removed when reading and written back when the file had one
+use encoding_rs::{CoderResult, Decoder, DecoderResult, Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+// the number of bytes decoded at once
+const CHUNK_SIZE: usize = 8 * 1024;
+
+// reads a file as utf-8, the bytes that are not valid in the encoding are replaced by � when the
+// reader is lossy, else the reading fails at the line that holds them
+pub struct DecodingReader<R: Read> {
+    inner: R,
+    decoder: Decoder,
+    lossy: bool,
+    // the decoded text that is not read yet
+    decoded: String,
+    pos: usize,
+    finished: bool,
+    malformed: bool,
+    // some bytes were replaced
+    pub replaced: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub fn new(inner: R, encoding: &'static Encoding, lossy: bool) -> Self {
+        DecodingReader {
+            inner,
+            // the byte order mark is removed, a utf-16 mark changes the encoding
+            decoder: encoding.new_decoder(),
+            lossy,
+            decoded: String::new(),
+            pos: 0,
+            finished: false,
+            malformed: false,
+            replaced: false,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let read = self.inner.read(&mut chunk)?;
+        // the decoder is told when the input ends so a cut sequence is not valid
+        let last = read == 0;
+        self.decoded.clear();
+        self.pos = 0;
+        let needed = self.decoder.max_utf8_buffer_length(read);
+        self.decoded.reserve(needed.unwrap_or(read * 3 + 16));
+        if self.lossy {
+            let (result, _, replaced) =
+                self.decoder
+                    .decode_to_string(&chunk[..read], &mut self.decoded, last);
+            debug_assert!(result == CoderResult::InputEmpty);
+            self.replaced |= replaced;
+        } else {
+            let (result, _) = self.decoder.decode_to_string_without_replacement(
+                &chunk[..read],
+                &mut self.decoded,
+                last,
+            );
+            // the text before the invalid bytes is read first
+            self.malformed = matches!(result, DecoderResult::Malformed(_, _));
+        }
+        self.finished = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            if self.malformed {
+                // the error is returned once, the rest of the file is not read
+                self.malformed = false;
+                self.finished = true;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("the text is not valid {}", self.decoder.encoding().name()),
+                ));
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let bytes = &self.decoded.as_bytes()[self.pos..];
+        let count = bytes.len().min(buf.len());
+        buf[..count].copy_from_slice(&bytes[..count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+// the encoding of a name (ex: latin1, utf-16, windows-1252)
+pub fn from_name(name: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(name.trim().as_bytes())
+}
+
+// encodes the text, the error is the first character the encoding can not represent
+pub fn encode<'a>(text: &'a str, encoding: &'static Encoding) -> Result<Cow<'a, [u8]>, char> {
+    // encoding_rs only decodes utf-16
+    if encoding == UTF_16LE {
+        return Ok(Cow::Owned(text.encode_utf16().flat_map(u16::to_le_bytes).collect()));
+    }
+    if encoding == UTF_16BE {
+        return Ok(Cow::Owned(text.encode_utf16().flat_map(u16::to_be_bytes).collect()));
+    }
+    let (bytes, _, unmappable) = encoding.encode(text);
+    if !unmappable {
+        return Ok(bytes);
+    }
+    // encoding_rs writes the character as &#N; instead
+    let mut buffer = [0u8; 4];
+    Err(text
+        .chars()
+        .find(|c| encoding.encode(c.encode_utf8(&mut buffer)).2)
+        .unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+pub fn byte_order_mark(encoding: &'static Encoding) -> &'static [u8] {
+    if encoding == UTF_8 {
+        &[0xEF, 0xBB, 0xBF]
+    } else if encoding == UTF_16LE {
+        &[0xFF, 0xFE]
+    } else if encoding == UTF_16BE {
+        &[0xFE, 0xFF]
+    } else {
+        &[]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+    use std::io::{BufRead, BufReader};
+
+    fn read_lines(
+        bytes: &[u8],
+        encoding: &'static Encoding,
+        lossy: bool,
+    ) -> Vec<io::Result<String>> {
+        BufReader::new(DecodingReader::new(bytes, encoding, lossy))
+            .lines()
+            .collect()
+    }
+
+    #[test]
+    fn decode_the_lines() {
+        let lines = read_lines(b"\xEF\xBB\xBFid\r\nJos\xC3\xA9\r\n", UTF_8, false);
+        let lines: Vec<String> = lines.into_iter().map(|line| line.unwrap()).collect();
+        assert_eq!(lines, vec!["id", "José"]);
+
+        let lines = read_lines(b"id\nJos\xE9\n", from_name("latin1").unwrap(), false);
+        assert_eq!(lines[1].as_ref().unwrap(), "José");
+
+        let bytes = encode("id\nJosé\n", UTF_16LE).unwrap();
+        let lines = read_lines(&[byte_order_mark(UTF_16LE), &bytes].concat(), UTF_8, false);
+        assert_eq!(lines[1].as_ref().unwrap(), "José");
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let lines = read_lines(b"id\nJos\xE9\nann\n", UTF_8, false);
+        assert!(lines[0].is_ok());
+        assert!(lines[1].is_err());
+
+        let lines = read_lines(b"id\nJos\xE9\n", UTF_8, true);
+        assert_eq!(lines[1].as_ref().unwrap(), "Jos\u{FFFD}");
+    }
+
+    #[test]
+    fn encode_the_text() {
+        assert_eq!(encode("José", WINDOWS_1252).unwrap().as_ref(), b"Jos\xE9");
+        assert_eq!(encode("€ and ☃", WINDOWS_1252), Err('☃'));
+    }
+}
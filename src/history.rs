@@ -0,0 +1,383 @@
+// the undo and redo history of a table, every mutating command is stored as the small change it
+// made (the modified cells, the removed rows...) so it can be applied again or reverted
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellChange {
+    pub row: usize,
+    pub field: usize,
+    pub old: String,
+    pub new: String,
+}
+
+// a change of the columns: every new column comes from an old column or was computed, the values
+// of the old columns that are not kept are stored so they can be restored
+#[derive(Debug, Clone)]
+pub struct ColumnsChange {
+    old_fields: Vec<String>,
+    new_fields: Vec<String>,
+    layout: Vec<Option<usize>>,
+    // the values of the computed columns (the None entries of the layout)
+    added: Vec<Vec<String>>,
+    dropped: Vec<(usize, Vec<String>)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Change {
+    Cells(Vec<CellChange>),
+    // the removed rows with their index in the table before the removal
+    RemovedRows(Vec<(usize, Vec<String>)>),
+    // the rows added at the end of the table
+    AddedRows(Vec<Vec<String>>),
+    Columns(ColumnsChange),
+}
+
+impl ColumnsChange {
+    // a change that builds every new column from the old column at the same index in layout
+    pub fn new(
+        fields: &[String],
+        rows: &[Vec<String>],
+        new_fields: Vec<String>,
+        layout: &[usize],
+    ) -> Self {
+        let dropped = (0..fields.len())
+            .filter(|i| !layout.contains(i))
+            .map(|i| (i, rows.iter().map(|row| row[i].clone()).collect()))
+            .collect();
+        ColumnsChange {
+            old_fields: fields.to_vec(),
+            new_fields,
+            layout: layout.iter().map(|&i| Some(i)).collect(),
+            added: Vec::new(),
+            dropped,
+        }
+    }
+
+    // the change made by adding columns at the end of the table, called after the columns were added
+    pub fn appended(old_fields: Vec<String>, fields: &[String], rows: &[Vec<String>]) -> Self {
+        let kept = old_fields.len();
+        let added = (kept..fields.len())
+            .map(|i| rows.iter().map(|row| row[i].clone()).collect())
+            .collect();
+        let layout = (0..fields.len())
+            .map(|i| if i < kept { Some(i) } else { None })
+            .collect();
+        ColumnsChange {
+            old_fields,
+            new_fields: fields.to_vec(),
+            layout,
+            added,
+            dropped: Vec::new(),
+        }
+    }
+
+    pub fn old_fields(&self) -> &Vec<String> {
+        &self.old_fields
+    }
+
+    pub fn new_fields(&self) -> &Vec<String> {
+        &self.new_fields
+    }
+
+    fn redo(&self, fields: &mut Vec<String>, rows: &mut [Vec<String>]) {
+        for (r, row) in rows.iter_mut().enumerate() {
+            let mut added = self.added.iter();
+            *row = self
+                .layout
+                .iter()
+                .map(|source| match source {
+                    Some(i) => row[*i].clone(),
+                    None => added
+                        .next()
+                        .map_or(String::new(), |values| values[r].clone()),
+                })
+                .collect();
+        }
+        *fields = self.new_fields.clone();
+    }
+
+    fn undo(&self, fields: &mut Vec<String>, rows: &mut [Vec<String>]) {
+        for (r, row) in rows.iter_mut().enumerate() {
+            *row = (0..self.old_fields.len())
+                .map(
+                    |i| match self.layout.iter().position(|&source| source == Some(i)) {
+                        Some(new_idx) => row[new_idx].clone(),
+                        None => self
+                            .dropped
+                            .iter()
+                            .find(|(idx, _)| *idx == i)
+                            .map_or(String::new(), |(_, values)| values[r].clone()),
+                    },
+                )
+                .collect();
+        }
+        *fields = self.old_fields.clone();
+    }
+}
+
+impl Change {
+    // a short description of the change printed after the command (ex: 2 rows deleted)
+    pub fn summary(&self) -> String {
+        match self {
+            Change::Cells(cells) => {
+                let rows: HashSet<usize> = cells.iter().map(|cell| cell.row).collect();
+                format!("{} rows modified, {} values changed", rows.len(), cells.len())
+            }
+            Change::RemovedRows(removed) => format!("{} rows deleted", removed.len()),
+            Change::AddedRows(added) => format!("{} rows added", added.len()),
+            Change::Columns(columns) => format!(
+                "{} columns before, {} columns after",
+                columns.old_fields.len(),
+                columns.new_fields.len()
+            ),
+        }
+    }
+
+    pub fn redo(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
+        match self {
+            Change::Cells(cells) => {
+                for cell in cells.iter() {
+                    rows[cell.row][cell.field] = cell.new.clone();
+                }
+            }
+            Change::RemovedRows(removed) => {
+                // the indexes are in increasing order so the last rows are removed first
+                for (idx, _) in removed.iter().rev() {
+                    rows.remove(*idx);
+                }
+            }
+            Change::AddedRows(added) => rows.extend(added.iter().cloned()),
+            Change::Columns(columns) => columns.redo(fields, rows),
+        }
+    }
+
+    pub fn undo(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
+        match self {
+            Change::Cells(cells) => {
+                for cell in cells.iter().rev() {
+                    rows[cell.row][cell.field] = cell.old.clone();
+                }
+            }
+            Change::RemovedRows(removed) => {
+                for (idx, row) in removed.iter() {
+                    rows.insert(*idx, row.clone());
+                }
+            }
+            Change::AddedRows(added) => rows.truncate(rows.len() - added.len()),
+            Change::Columns(columns) => columns.undo(fields, rows),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Change::Cells(cells) => cells.is_empty(),
+            Change::RemovedRows(removed) => removed.is_empty(),
+            Change::AddedRows(added) => added.is_empty(),
+            Change::Columns(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    // every change gets a new id, so an id tells which state of the table it leads to
+    id: u64,
+    command: String,
+    change: Change,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    done: Vec<Entry>,
+    undone: Vec<Entry>,
+    last_id: u64,
+}
+
+impl History {
+    // a new change can not be followed by the changes that were undone before it
+    pub fn record(&mut self, command: &str, change: Change) {
+        if change.is_empty() {
+            return;
+        }
+        self.last_id += 1;
+        self.done.push(Entry {
+            id: self.last_id,
+            command: command.to_string(),
+            change,
+        });
+        self.undone.clear();
+    }
+
+    // reverts the last change, returns its command or None if there is nothing to undo
+    pub fn undo(
+        &mut self,
+        fields: &mut Vec<String>,
+        rows: &mut Vec<Vec<String>>,
+    ) -> Option<String> {
+        let entry = self.done.pop()?;
+        entry.change.undo(fields, rows);
+        let command = entry.command.clone();
+        self.undone.push(entry);
+        Some(command)
+    }
+
+    pub fn redo(
+        &mut self,
+        fields: &mut Vec<String>,
+        rows: &mut Vec<Vec<String>>,
+    ) -> Option<String> {
+        let entry = self.undone.pop()?;
+        entry.change.redo(fields, rows);
+        let command = entry.command.clone();
+        self.done.push(entry);
+        Some(command)
+    }
+
+    // identifies the current state of the table, 0 for the state it was loaded in
+    pub fn version(&self) -> u64 {
+        self.done.last().map_or(0, |entry| entry.id)
+    }
+
+    // the number of changes to undo or redo to go back to the state of the given version, or the
+    // number of done changes if that state can not be reached anymore
+    pub fn changes_since(&self, version: u64) -> usize {
+        if let Some(idx) = self.done.iter().position(|entry| entry.id == version) {
+            return self.done.len() - idx - 1;
+        }
+        if version == 0 {
+            return self.done.len();
+        }
+        // the changes undone last are at the end of the undone list
+        match self.undone.iter().rposition(|entry| entry.id == version) {
+            Some(idx) => self.undone.len() - idx,
+            None => self.done.len(),
+        }
+    }
+
+    // the commands that can be undone, the oldest first
+    pub fn commands(&self) -> impl Iterator<Item = &String> {
+        self.done.iter().map(|entry| &entry.command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|v| v.to_string()).collect())
+            .collect()
+    }
+
+    fn get_data() -> (Vec<String>, Vec<Vec<String>>) {
+        let fields = vec!["id".to_string(), "name".to_string(), "age".to_string()];
+        let rows = to_rows(&[
+            &["1", "bob", "30"],
+            &["2", "jack", "25"],
+            &["3", "ann", "41"],
+        ]);
+        (fields, rows)
+    }
+
+    // applies the change with redo then checks that undo gives back the original table
+    fn check_undo(change: Change) -> (Vec<String>, Vec<Vec<String>>) {
+        let (mut fields, mut rows) = get_data();
+        change.redo(&mut fields, &mut rows);
+        let result = (fields.clone(), rows.clone());
+        change.undo(&mut fields, &mut rows);
+        assert_eq!((fields, rows), get_data());
+        result
+    }
+
+    #[test]
+    fn cells() {
+        let change = Change::Cells(vec![CellChange {
+            row: 1,
+            field: 2,
+            old: "25".to_string(),
+            new: "26".to_string(),
+        }]);
+        let (_, rows) = check_undo(change);
+        assert_eq!(rows[1][2], "26");
+    }
+
+    #[test]
+    fn removed_rows() {
+        let (_, rows) = get_data();
+        let change = Change::RemovedRows(vec![(0, rows[0].clone()), (2, rows[2].clone())]);
+        let (_, rows) = check_undo(change);
+        assert_eq!(rows, to_rows(&[&["2", "jack", "25"]]));
+    }
+
+    #[test]
+    fn added_rows() {
+        let change = Change::AddedRows(to_rows(&[&["4", "zed", "19"]]));
+        let (_, rows) = check_undo(change);
+        assert_eq!(rows.len(), 4);
+    }
+
+    #[test]
+    fn dropped_and_moved_columns() {
+        let (fields, rows) = get_data();
+        let new_fields = vec!["age".to_string(), "id".to_string()];
+        let change = Change::Columns(ColumnsChange::new(&fields, &rows, new_fields, &[2, 0]));
+        let (fields, rows) = check_undo(change);
+        assert_eq!(fields, vec!["age", "id"]);
+        assert_eq!(rows[0], vec!["30", "1"]);
+    }
+
+    #[test]
+    fn appended_columns() {
+        let (old_fields, mut rows) = get_data();
+        let mut fields = old_fields.clone();
+        fields.push("bonus".to_string());
+        for row in rows.iter_mut() {
+            row.push("5".to_string());
+        }
+        let change = ColumnsChange::appended(old_fields, &fields, &rows);
+        let (fields, rows) = check_undo(Change::Columns(change));
+        assert_eq!(fields.len(), 4);
+        assert_eq!(rows[2][3], "5");
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let (mut fields, mut rows) = get_data();
+        let mut history = History::default();
+        assert!(history.undo(&mut fields, &mut rows).is_none());
+        let change = Change::AddedRows(to_rows(&[&["4", "zed", "19"]]));
+        change.redo(&mut fields, &mut rows);
+        history.record("insert-row $id = 4", change);
+        assert_eq!(
+            history.undo(&mut fields, &mut rows).as_deref(),
+            Some("insert-row $id = 4")
+        );
+        assert_eq!(rows.len(), 3);
+        assert!(history.commands().next().is_none());
+        history.redo(&mut fields, &mut rows);
+        assert_eq!(rows.len(), 4);
+        assert!(history.redo(&mut fields, &mut rows).is_none());
+    }
+
+    #[test]
+    fn changes_since_a_version() {
+        let (mut fields, mut rows) = get_data();
+        let mut history = History::default();
+        let added = |id: &str| Change::AddedRows(to_rows(&[&[id, "zed", "19"]]));
+        history.record("insert-row $id = 4", added("4"));
+        let saved = history.version();
+        assert_eq!(history.changes_since(saved), 0);
+        history.record("insert-row $id = 5", added("5"));
+        history.record("insert-row $id = 6", added("6"));
+        assert_eq!(history.changes_since(saved), 2);
+        assert_eq!(history.changes_since(0), 3);
+        history.undo(&mut fields, &mut rows);
+        history.undo(&mut fields, &mut rows);
+        assert_eq!(history.changes_since(saved), 0);
+        history.undo(&mut fields, &mut rows);
+        assert_eq!(history.version(), 0);
+        // the saved change was undone, it has to be redone
+        assert_eq!(history.changes_since(saved), 1);
+    }
+}
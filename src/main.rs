@@ -2,34 +2,144 @@ use regex::Regex;
 use std::collections::HashMap;
 use std::sync::OnceLock;
 use std::{env, process::exit};
+mod compression;
 mod csv_parser;
+mod editor;
+mod encoding;
+mod history;
 mod logger;
 mod query_engine;
+mod sampling;
+mod schema;
+mod session;
+mod sniffer;
+mod statements;
 
 pub static OPTIONS: OnceLock<HashMap<Options, String>> = OnceLock::new();
 
 #[derive(Hash, PartialEq, Eq)]
 pub enum Options {
     FieldsSeparator,
+    Sample,
+    Seed,
+    ConfirmOver,
+    Backup,
+    Schema,
+    OnBadRow,
+    NoHeader,
+    HasHeader,
+    Header,
+    SkipLines,
+    Quote,
+    OutputSeparator,
+    Encoding,
+    Lossy,
 }
 
 fn main() {
     // the regexs for all the possible options
-    let fields_sep_regex = Regex::new(r"-s.").unwrap();
+    let fields_sep_regex = Regex::new(r"^-s.+$").unwrap();
+    let output_sep_regex = Regex::new(r"^--output-separator=.+$").unwrap();
+    let sample_regex = Regex::new(r"^--sample=(\d+|\d+(\.\d+)?%)$").unwrap();
+    let seed_regex = Regex::new(r"^--seed=\d+$").unwrap();
+    let confirm_over_regex = Regex::new(r"^--confirm-over=\d+$").unwrap();
+    let schema_regex = Regex::new(r"^--schema=.+\.(toml|json)$").unwrap();
+    let on_bad_row_regex = Regex::new(r"^--on-bad-row=(skip|fail|pad|keep-raw)$").unwrap();
+    let header_regex = Regex::new(r"^--header=.+$").unwrap();
+    let skip_lines_regex = Regex::new(r"^--skip-lines=\d+$").unwrap();
+    let encoding_regex = Regex::new(r"^--encoding=.+$").unwrap();
+    let quote_regex = Regex::new(r"^--quote=(.|none)$").unwrap();
 
     let args: Vec<String> = env::args().collect();
-    if args.len() < 1 {
+    if args.len() < 2 {
         log_error!("usage: CSVParser [options] file");
         exit(1);
     }
 
+    // the options that are not given keep their default value or are guessed from the file
     let mut options: HashMap<Options, String> = HashMap::new();
-    set_default_options(&mut options);
+    // --header and --skip-lines can also be followed by their value (ex: --skip-lines 2)
+    let mut option_args: Vec<String> = Vec::new();
+    let mut rest = args[1..args.len() - 1].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--header" | "--skip-lines" if rest.len() > 0 => {
+                option_args.push(format!("{arg}={}", rest.next().unwrap()))
+            }
+            _ => option_args.push(arg.clone()),
+        }
+    }
     // parse options
-    for option in &args[2..args.len() - 1] {
+    for option in &option_args {
         match option {
-            op if fields_sep_regex.is_match(option) => {
-                options.insert(Options::FieldsSeparator, op[2..].to_string())
+            op if fields_sep_regex.is_match(option) => match csv_parser::unescape(&op[2..]) {
+                Some(separator) => options.insert(Options::FieldsSeparator, separator),
+                None => {
+                    log_error!(
+                        "invalid separator {}, the escapes are \\t, \\\\ and \\xHH",
+                        &op[2..]
+                    );
+                    return;
+                }
+            },
+            op if output_sep_regex.is_match(option) => {
+                let separator = &op["--output-separator=".len()..];
+                match csv_parser::unescape(separator) {
+                    Some(separator) => options.insert(Options::OutputSeparator, separator),
+                    None => {
+                        log_error!(
+                            "invalid separator {}, the escapes are \\t, \\\\ and \\xHH",
+                            separator
+                        );
+                        return;
+                    }
+                }
+            }
+            op if encoding_regex.is_match(option) => {
+                let name = &op["--encoding=".len()..];
+                if encoding::from_name(name).is_none() {
+                    log_error!("unknown encoding {} (ex: utf-8, latin1, utf-16, windows-1252)", name);
+                    return;
+                }
+                options.insert(Options::Encoding, name.to_string())
+            }
+            op if op == "--lossy" => options.insert(Options::Lossy, String::new()),
+            op if op == "--tsv" => options.insert(Options::FieldsSeparator, "\t".to_string()),
+            op if op == "--psv" => options.insert(Options::FieldsSeparator, "|".to_string()),
+            op if op == "--ssv" => options.insert(Options::FieldsSeparator, ";".to_string()),
+            op if sample_regex.is_match(option) => {
+                let size = &op["--sample=".len()..];
+                // the percentage must also be at most 100
+                if sampling::SampleSize::parse(size).is_none() {
+                    log_error!("invalid sample size {}, use a number of rows or a percentage", size);
+                    return;
+                }
+                options.insert(Options::Sample, size.to_string())
+            }
+            op if seed_regex.is_match(option) => {
+                options.insert(Options::Seed, op["--seed=".len()..].to_string())
+            }
+            op if op == "--backup" => options.insert(Options::Backup, String::new()),
+            op if confirm_over_regex.is_match(option) => options.insert(
+                Options::ConfirmOver,
+                op["--confirm-over=".len()..].to_string(),
+            ),
+            op if schema_regex.is_match(option) => {
+                options.insert(Options::Schema, op["--schema=".len()..].to_string())
+            }
+            op if on_bad_row_regex.is_match(option) => {
+                options.insert(Options::OnBadRow, op["--on-bad-row=".len()..].to_string())
+            }
+            op if op == "--no-header" => options.insert(Options::NoHeader, String::new()),
+            op if op == "--has-header" => options.insert(Options::HasHeader, String::new()),
+            op if header_regex.is_match(option) => {
+                options.insert(Options::Header, op["--header=".len()..].to_string())
+            }
+            op if quote_regex.is_match(option) => {
+                options.insert(Options::Quote, op["--quote=".len()..].to_string())
+            }
+            op if skip_lines_regex.is_match(option) => {
+                options.insert(Options::SkipLines, op["--skip-lines=".len()..].to_string())
             }
             _ => {
                 log_error!("invalid option {}", option);
@@ -37,10 +147,10 @@ fn main() {
             }
         };
     }
+    if options.contains_key(&Options::NoHeader) && options.contains_key(&Options::HasHeader) {
+        log_error!("--has-header and --no-header can not be used together");
+        return;
+    }
     let _ = OPTIONS.set(options);
     csv_parser::run(args[args.len() - 1].as_str());
 }
-
-fn set_default_options(options: &mut HashMap<Options, String>) {
-    options.insert(Options::FieldsSeparator, ",".to_string());
-}
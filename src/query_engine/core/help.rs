@@ -0,0 +1,374 @@
+// the help command, the names of the operators, the modifiers and the functions are taken from the
+// tables the parsers look them up in so a new name shows up in the help
+use super::tokens::aggregation_function_call::AggregationFunction;
+use super::tokens::comparison::ComparisonOps;
+use super::tokens::function::Functions;
+use super::tokens::modification;
+use crate::log_error;
+
+// the name, the usage and the description of a help topic
+type Topic = (&'static str, &'static str, &'static str);
+
+const STATEMENTS: [Topic; 16] = [
+    (
+        "get",
+        "get (* | $field ($field)*) (from ...)? (where CONDITION)? FUNCTION*",
+        "print the rows of the table",
+    ),
+    (
+        "set",
+        "set $field = MODIFICATION ($field = MODIFICATION)* (where CONDITION)?",
+        "change the values of the rows",
+    ),
+    (
+        "delete",
+        "delete where CONDITION",
+        "delete the rows that satisfy the condition",
+    ),
+    (
+        "insert-row",
+        "insert-row $field = VALUE ($field = VALUE)*",
+        "add a row, the missing fields are empty",
+    ),
+    (
+        "insert-column",
+        "insert-column $field = MODIFICATION (where CONDITION)?",
+        "add a column computed from the other ones",
+    ),
+    (
+        "duplicates",
+        "duplicates $field ($field)* (where CONDITION)?",
+        "print the rows that repeat the same key",
+    ),
+    (
+        "union",
+        "get ... union (all)? get ...",
+        "the rows of both queries, union all keeps the repeated rows",
+    ),
+    (
+        "intersect",
+        "get ... intersect get ...",
+        "the rows that are in both queries",
+    ),
+    (
+        "except",
+        "get ... except get ...",
+        "the rows of the first query that are not in the second one",
+    ),
+    (
+        "diff",
+        "diff \"old.csv\" \"new.csv\" key $field (to \"patch.csv\")?",
+        "compare two files on a key",
+    ),
+    (
+        "drop-column",
+        "drop-column $field ($field)*",
+        "remove columns",
+    ),
+    (
+        "rename-column",
+        "rename-column $old to $new",
+        "rename a column",
+    ),
+    (
+        "move-column",
+        "move-column $field (after | before) $field",
+        "move a column next to an other one",
+    ),
+    (
+        "select-columns",
+        "select-columns $field ($field)*",
+        "keep only the given columns in the given order",
+    ),
+    (
+        "copy-column",
+        "copy-column $field to $new",
+        "add a copy of a column",
+    ),
+    (
+        "explain-changes",
+        "explain-changes (SET | DELETE)",
+        "show what a set or a delete would change without applying it",
+    ),
+];
+
+const COMMANDS: [Topic; 20] = [
+    (
+        "open",
+        "open \"file.csv\" (as name)? (separator \"x\")? (schema \"schema.toml\")?",
+        "load an other file as a table",
+    ),
+    ("use", "use name", "switch the current table"),
+    ("tables", "tables", "list the open tables"),
+    (
+        "close",
+        "close (name)?",
+        "close the current table or a table by name",
+    ),
+    (
+        "bad-rows",
+        "bad-rows",
+        "list the lines that do not have as many values as the header, a kept raw line is saved \
+         before the row number that followed it when the file was loaded, even after the rows moved",
+    ),
+    ("undo", "undo", "revert the last change"),
+    ("redo", "redo", "apply the last undone change again"),
+    ("history", "history", "list the changes that can be undone"),
+    (
+        "save",
+        "save (as \"file.csv\")?",
+        "write the table to its file or to an other file",
+    ),
+    (
+        "export",
+        "export \"file.csv\" (separator \"x\")? (--compress=<gzip|zstd|none>)?",
+        "write a copy of the table, compressed as its extension or --compress tells",
+    ),
+    ("begin", "begin", "start a transaction"),
+    ("commit", "commit", "keep the changes of the transaction"),
+    (
+        "rollback",
+        "rollback (to name)?",
+        "cancel the transaction or the changes since a savepoint",
+    ),
+    (
+        "savepoint",
+        "savepoint name",
+        "mark a state of the transaction",
+    ),
+    (
+        "source",
+        "source \"script.txt\"",
+        "run the statements of a file as one batch",
+    ),
+    (
+        "help",
+        "help (name)?",
+        "list the help topics or show one of them",
+    ),
+    (
+        "describe",
+        "describe",
+        "the type, the empty values and the distinct values of every column",
+    ),
+    ("fields", "fields", "list the fields of the current table"),
+    (
+        "profile",
+        "profile ($field)* (top N)? (to \"report.json\")?",
+        "a data quality report of the columns",
+    ),
+    ("quit", "quit", "leave, the unsaved changes can be saved"),
+];
+
+const CLAUSES: [Topic; 2] = [
+    (
+        "where",
+        "where CONDITION",
+        "CONDITION = $field OPERATOR VALUE combined with and, or, not and ( )",
+    ),
+    (
+        "from",
+        "from \"file.csv\" (as name)? ((inner | left | anti)? join SOURCE on $a == $b)*",
+        "query or join other files and tables",
+    ),
+];
+
+const OPERATORS: [Topic; 13] = [
+    ("==", "$field == VALUE", "equal"),
+    ("!=", "$field != VALUE", "not equal"),
+    ("<", "$field < VALUE", "less than"),
+    (">", "$field > VALUE", "greater than"),
+    ("<=", "$field <= VALUE", "less than or equal"),
+    (">=", "$field >= VALUE", "greater than or equal"),
+    (
+        "between",
+        "$field between VALUE and VALUE",
+        "between two numbers or fields",
+    ),
+    ("is", "$field is \"text\"", "equal strings"),
+    ("isnot", "$field isnot \"text\"", "not equal strings"),
+    (
+        "contains",
+        "$field contains \"text\"",
+        "if a string contains an other one",
+    ),
+    (
+        "starts-with",
+        "$field starts-with \"text\"",
+        "if a string starts with an other one",
+    ),
+    (
+        "ends-with",
+        "$field ends-with \"text\"",
+        "if a string ends with an other one",
+    ),
+    (
+        "in",
+        "$field in [VALUE, VALUE]",
+        "if the value is in the given list",
+    ),
+];
+
+const MODIFIERS: [Topic; 9] = [
+    ("+", "VALUE + VALUE", "addition"),
+    ("-", "VALUE - VALUE", "subtraction"),
+    ("*", "VALUE * VALUE", "multiplication"),
+    ("/", "VALUE / VALUE", "division"),
+    ("%", "VALUE % VALUE", "modulo (remainder)"),
+    ("^", "VALUE ^ VALUE", "power (exponentiation)"),
+    ("||", "VALUE || VALUE", "concatenate strings"),
+    (
+        "to-upper",
+        "VALUE to-upper",
+        "convert a string to uppercase",
+    ),
+    (
+        "to-lower",
+        "VALUE to-lower",
+        "convert a string to lowercase",
+    ),
+];
+
+const FUNCTIONS: [Topic; 13] = [
+    ("sort", "sort $field", "sort in ascii order"),
+    ("rsort", "rsort $field", "sort in reverse ascii order"),
+    ("nsort", "nsort $field", "sort in numeric order"),
+    ("rnsort", "rnsort $field", "sort in reverse numeric order"),
+    ("head", "head N", "get the first n rows"),
+    ("tail", "tail N", "get the last n rows"),
+    (
+        "distinct",
+        "distinct",
+        "remove the rows that repeat the selected fields",
+    ),
+    (
+        "distinct-on",
+        "distinct-on $field",
+        "keep the first row for each value of the field",
+    ),
+    ("offset", "offset N", "skip the first n rows"),
+    ("limit", "limit N", "keep at most n rows"),
+    ("page", "page N size M", "get the nth page of m rows"),
+    ("sample", "sample (N | N%) (seed N)?", "get n random rows"),
+    (
+        "shuffle",
+        "shuffle (seed N)?",
+        "put the rows in a random order",
+    ),
+];
+
+const AGGREGATIONS: [Topic; 5] = [
+    (
+        "sum",
+        "sum MODIFICATION (where CONDITION)?",
+        "calculate the sum",
+    ),
+    (
+        "avg",
+        "avg MODIFICATION (where CONDITION)?",
+        "calculate the average",
+    ),
+    (
+        "mean",
+        "mean MODIFICATION (where CONDITION)?",
+        "calculate the mean",
+    ),
+    (
+        "count",
+        "count MODIFICATION (where CONDITION)?",
+        "count the values that satisfy the where clause",
+    ),
+    (
+        "counter",
+        "counter MODIFICATION (where CONDITION)?",
+        "count the occurrences of every value",
+    ),
+];
+
+// the titles of the help topics with their names, the names of the operators and the functions
+// come from the parsers
+fn groups() -> Vec<(&'static str, Vec<&'static str>)> {
+    let names = |topics: &[Topic]| topics.iter().map(|(name, _, _)| *name).collect();
+    vec![
+        ("statements", names(&STATEMENTS)),
+        ("commands", names(&COMMANDS)),
+        ("clauses", names(&CLAUSES)),
+        ("operators", ComparisonOps::get_available_operators_names()),
+        ("modifiers", modification::get_available_modifiers_names()),
+        ("functions", Functions::get_available_functions_names()),
+        (
+            "aggregations",
+            AggregationFunction::get_available_functions_names(),
+        ),
+    ]
+}
+
+// the names of all the topics
+pub fn names() -> Vec<&'static str> {
+    groups().into_iter().flat_map(|(_, names)| names).collect()
+}
+
+fn find(name: &str) -> Option<&'static Topic> {
+    [
+        &STATEMENTS[..],
+        &COMMANDS,
+        &CLAUSES,
+        &OPERATORS,
+        &MODIFIERS,
+        &FUNCTIONS,
+        &AGGREGATIONS,
+    ]
+    .into_iter()
+    .flatten()
+    .find(|(topic, _, _)| *topic == name)
+}
+
+pub fn help(topic: Option<&str>) -> bool {
+    let Some(name) = topic else {
+        for (title, names) in groups() {
+            println!("{title}: {}", names.join(" "));
+        }
+        println!("\nuse help NAME for the usage of one of them (ex: help between)");
+        return true;
+    };
+    match find(name) {
+        Some((_, usage, description)) => {
+            println!("{usage}\n    {description}");
+            true
+        }
+        None => {
+            log_error!("no help on {}, use help to list the topics", name);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_name_has_a_topic() {
+        for (title, names) in groups() {
+            for name in names {
+                assert!(find(name).is_some(), "no help on the {title} {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_topic_is_parsed() {
+        let groups = groups();
+        let names = |title: &str| &groups.iter().find(|(group, _)| *group == title).unwrap().1;
+        for (title, topics) in [
+            ("operators", &OPERATORS[..]),
+            ("modifiers", &MODIFIERS),
+            ("functions", &FUNCTIONS),
+            ("aggregations", &AGGREGATIONS),
+        ] {
+            for (name, _, _) in topics {
+                assert!(names(title).contains(name), "the {title} {name} is not parsed");
+            }
+        }
+    }
+}
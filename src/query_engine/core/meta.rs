@@ -0,0 +1,62 @@
+// the commands that describe the language and the tables, they are not part of the query grammar
+use super::help;
+use super::lexemes;
+use super::profile::{self, Profile};
+use super::tokens::get_query::GetQuery;
+use super::tokens::value;
+use crate::log_error;
+use crate::session::{Session, Table};
+use std::collections::HashSet;
+
+// returns None if the command is not a meta command
+pub fn command(command: &str, session: &Session) -> Option<bool> {
+    let lexemes = lexemes(command);
+    let words: Vec<&str> = lexemes
+        .iter()
+        .map(|lexeme| lexeme.as_str())
+        .filter(|lexeme| !lexeme.is_empty())
+        .collect();
+    match words.as_slice() {
+        ["help"] => Some(help::help(None)),
+        ["help", name] => Some(help::help(Some(name))),
+        ["help", ..] => {
+            log_error!("usage: help (name)?");
+            Some(false)
+        }
+        ["describe"] => {
+            describe(session.current());
+            Some(true)
+        }
+        ["profile", args @ ..] => {
+            Some(Profile::parse(args).is_some_and(|profile| profile.evaluate(session.current())))
+        }
+        ["fields"] => {
+            let table = session.current();
+            for (i, field) in table.fields.iter().enumerate() {
+                println!("{} {}", i + 1, value::field_reference(field));
+            }
+            Some(true)
+        }
+        _ => None,
+    }
+}
+
+fn describe(table: &Table) {
+    let rows: Vec<Vec<String>> = (0..table.fields.len())
+        .map(|i| {
+            let values: Vec<&str> = table.rows.iter().map(|row| row[i].as_str()).collect();
+            let distinct: HashSet<&&str> = values.iter().filter(|v| !v.is_empty()).collect();
+            vec![
+                table.fields[i].clone(),
+                profile::infer_type(&values).0.to_string(),
+                values.iter().filter(|v| v.is_empty()).count().to_string(),
+                distinct.len().to_string(),
+            ]
+        })
+        .collect();
+    let fields = ["field", "type", "empty", "distinct"]
+        .map(|f| f.to_string())
+        .to_vec();
+    GetQuery::print_table(&fields, &rows.iter().collect());
+    println!("{} rows, {} fields", table.rows.len(), table.fields.len());
+}
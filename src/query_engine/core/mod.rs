@@ -1,11 +1,83 @@
+use crate::session::Session;
+
+mod help;
+mod meta;
+mod profile;
 mod tokens;
 
-pub fn query(lexemes: &[String], fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
+pub use meta::command as meta_command;
+pub use tokens::value::field_reference;
+
+// the words of the statements that are not help topics, the names of the statements, the commands,
+// the operators and the functions come from the help that takes them from the parsers
+const KEYWORDS: [&str; 17] = [
+    "as", "join", "inner", "left", "anti", "on", "and", "or", "not", "all", "key", "to", "after",
+    "before", "size", "seed", "top",
+];
+
+// the words offered by the tab completion, the operators that are signs (ex: ==) are not
+pub fn keywords() -> Vec<&'static str> {
+    let mut keywords = KEYWORDS.to_vec();
+    keywords.extend(
+        help::names()
+            .into_iter()
+            .filter(|name| name.starts_with(char::is_alphabetic)),
+    );
+    keywords
+}
+
+// splits a query into lexemes on the spaces, the spaces of a field reference between braces are kept
+// (ex: ${Total Sales})
+pub fn lexemes(query: &str) -> Vec<String> {
+    let mut lexemes: Vec<String> = Vec::new();
+    let mut lexeme = String::new();
+    let mut in_braces = false;
+    for c in query.chars() {
+        match c {
+            ' ' if !in_braces => lexemes.push(std::mem::take(&mut lexeme)),
+            '{' if lexeme.ends_with('$') => {
+                in_braces = true;
+                lexeme.push(c);
+            }
+            '}' if in_braces => {
+                in_braces = false;
+                lexeme.push(c);
+            }
+            _ => lexeme.push(c),
+        }
+    }
+    lexemes.push(lexeme);
+    lexemes
+}
+
+// returns false if the query could not be parsed or failed
+pub fn query(lexemes: &[String], session: &mut Session) -> bool {
+    // the explain-changes (or --dry-run) prefix shows what the query would change
+    if let Some(prefix) = lexemes.first()
+        && (prefix == "explain-changes" || prefix == "--dry-run")
+    {
+        return match tokens::query::Query::parse(&lexemes[1..]) {
+            Some(q) => q.explain(session),
+            None => false,
+        };
+    }
     let query = tokens::query::Query::parse(lexemes);
     match query {
-        Some(q) => {
-            q.evaluate(fields, rows);
-        }
-        None => {}
+        Some(q) => q.evaluate(session, &lexemes.join(" ")),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_references_with_spaces() {
+        assert_eq!(
+            lexemes("get ${Total Sales} $name where ${a  b} > 3"),
+            vec!["get", "${Total Sales}", "$name", "where", "${a  b}", ">", "3"]
+        );
+        assert_eq!(lexemes("get  $x"), vec!["get", "", "$x"]);
     }
 }
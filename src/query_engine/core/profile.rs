@@ -0,0 +1,315 @@
+// a data quality report of the columns (ex: profile $age $salary top 3 to "report.json")
+use super::tokens::value;
+use crate::log_error;
+use crate::log_info;
+use crate::schema::Kind;
+use crate::session::Table;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs;
+
+// the number of most frequent values shown when top is not given
+const DEFAULT_TOP: usize = 5;
+
+#[derive(Debug, PartialEq)]
+pub struct Profile {
+    fields: Vec<String>,
+    top: usize,
+    path: Option<String>,
+}
+
+#[derive(Debug)]
+struct ColumnProfile {
+    field: String,
+    rows: usize,
+    empty: usize,
+    distinct: usize,
+    kind: &'static str,
+    // the non empty values that do not parse as the inferred type
+    invalid: usize,
+    min: Option<String>,
+    max: Option<String>,
+    mean: Option<f64>,
+    median: Option<f64>,
+    min_length: usize,
+    max_length: usize,
+    top: Vec<(String, usize)>,
+}
+
+impl Profile {
+    pub fn parse(args: &[&str]) -> Option<Self> {
+        let usage = "usage: profile ($field)* (top N)? (to \"report.json\")?";
+        let mut profile = Profile {
+            fields: Vec::new(),
+            top: DEFAULT_TOP,
+            path: None,
+        };
+        let mut i = 0;
+        while i < args.len() {
+            match (args[i], args.get(i + 1)) {
+                ("top", Some(n)) => match n.parse::<usize>() {
+                    Ok(n) => profile.top = n,
+                    Err(_) => {
+                        log_error!("top expects a number of values");
+                        return None;
+                    }
+                },
+                ("to", Some(path)) => match value::parse_literal(&path.to_string()) {
+                    Some(path) if path.ends_with(".json") => profile.path = Some(path),
+                    _ => {
+                        log_error!("the report should be written to a .json file");
+                        return None;
+                    }
+                },
+                (field, _) => match value::parse_field_name(&field.to_string()) {
+                    Some(field) if profile.path.is_none() => {
+                        profile.fields.push(field);
+                        i += 1;
+                        continue;
+                    }
+                    _ => {
+                        log_error!("{}", usage);
+                        return None;
+                    }
+                },
+            }
+            i += 2;
+        }
+        Some(profile)
+    }
+
+    pub fn evaluate(&self, table: &Table) -> bool {
+        let idxs: Vec<usize> = if self.fields.is_empty() {
+            (0..table.fields.len()).collect()
+        } else {
+            let mut idxs = Vec::new();
+            for field in self.fields.iter() {
+                match value::get_field_idx(&table.fields, field) {
+                    Some(idx) => idxs.push(idx),
+                    None => {
+                        log_error!("no field named {}", field);
+                        return false;
+                    }
+                }
+            }
+            idxs
+        };
+        let profiles: Vec<ColumnProfile> = idxs
+            .into_iter()
+            .map(|i| {
+                let values: Vec<&str> = table.rows.iter().map(|row| row[i].as_str()).collect();
+                ColumnProfile::new(&table.fields[i], &values, self.top)
+            })
+            .collect();
+        for profile in profiles.iter() {
+            profile.print();
+        }
+        match &self.path {
+            Some(path) => write_report(path, &profiles),
+            None => true,
+        }
+    }
+}
+
+impl ColumnProfile {
+    fn new(field: &str, values: &[&str], top: usize) -> Self {
+        let filled: Vec<&str> = values.iter().copied().filter(|v| !v.is_empty()).collect();
+        let (kind, invalid) = infer_type(values);
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for v in filled.iter() {
+            *counts.entry(v).or_default() += 1;
+        }
+        let distinct = counts.len();
+        let mut frequent: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(v, n)| (v.to_string(), n))
+            .collect();
+        // the most frequent first, the ties in the order of the values so the report is stable
+        frequent.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        frequent.truncate(top);
+
+        let mut numbers: Vec<f64> = Vec::new();
+        if kind == "integer" || kind == "number" {
+            numbers = filled
+                .iter()
+                .filter_map(|v| v.parse::<f64>().ok())
+                .collect();
+            numbers.sort_by(|a, b| a.total_cmp(b));
+        }
+        let (min, max) = if numbers.is_empty() {
+            (
+                filled.iter().min().map(|v| v.to_string()),
+                filled.iter().max().map(|v| v.to_string()),
+            )
+        } else {
+            (
+                numbers.first().map(|n| n.to_string()),
+                numbers.last().map(|n| n.to_string()),
+            )
+        };
+        let lengths = filled.iter().map(|v| v.chars().count());
+
+        ColumnProfile {
+            field: field.to_string(),
+            rows: values.len(),
+            empty: values.len() - filled.len(),
+            distinct,
+            kind,
+            invalid,
+            min,
+            max,
+            mean: mean(&numbers),
+            median: median(&numbers),
+            min_length: lengths.clone().min().unwrap_or(0),
+            max_length: lengths.max().unwrap_or(0),
+            top: frequent,
+        }
+    }
+
+    fn print(&self) {
+        println!("\n{}", value::field_reference(&self.field));
+        println!(
+            "  rows: {}, empty: {}, distinct: {}",
+            self.rows, self.empty, self.distinct
+        );
+        println!(
+            "  type: {} ({} values do not parse as {})",
+            self.kind, self.invalid, self.kind
+        );
+        if let (Some(min), Some(max)) = (&self.min, &self.max) {
+            println!("  min: {min}, max: {max}");
+        }
+        if let (Some(mean), Some(median)) = (self.mean, self.median) {
+            println!("  mean: {}, median: {}", round(mean), round(median));
+        }
+        println!("  length: {} to {}", self.min_length, self.max_length);
+        let top: Vec<String> = self.top.iter().map(|(v, n)| format!("{v} ({n})")).collect();
+        println!("  top: {}", top.join(", "));
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "field": self.field,
+            "rows": self.rows,
+            "empty": self.empty,
+            "distinct": self.distinct,
+            "type": self.kind,
+            "invalid": self.invalid,
+            "min": self.min,
+            "max": self.max,
+            "mean": self.mean,
+            "median": self.median,
+            "min_length": self.min_length,
+            "max_length": self.max_length,
+            "top": self.top.iter().map(|(v, n)| json!({"value": v, "count": n})).collect::<Vec<Value>>(),
+        })
+    }
+}
+
+// the narrowest type that most of the non empty values parse as, with the number of values that do
+// not parse as it
+pub fn infer_type(values: &[&str]) -> (&'static str, usize) {
+    let values: Vec<&&str> = values.iter().filter(|v| !v.is_empty()).collect();
+    if values.is_empty() {
+        return ("empty", 0);
+    }
+    for kind in [Kind::Integer, Kind::Number, Kind::Boolean] {
+        let valid = values.iter().filter(|v| kind.parses(v)).count();
+        if valid * 2 > values.len() {
+            return (kind.name(), values.len() - valid);
+        }
+    }
+    ("text", 0)
+}
+
+fn mean(numbers: &[f64]) -> Option<f64> {
+    if numbers.is_empty() {
+        return None;
+    }
+    Some(numbers.iter().sum::<f64>() / numbers.len() as f64)
+}
+
+// the numbers have to be sorted
+fn median(numbers: &[f64]) -> Option<f64> {
+    let middle = numbers.len() / 2;
+    match numbers.len() {
+        0 => None,
+        n if n % 2 == 0 => Some((numbers[middle - 1] + numbers[middle]) / 2.0),
+        _ => Some(numbers[middle]),
+    }
+}
+
+fn round(number: f64) -> f64 {
+    (number * 100.0).round() / 100.0
+}
+
+fn write_report(path: &str, profiles: &[ColumnProfile]) -> bool {
+    let report: Vec<Value> = profiles.iter().map(|p| p.to_json()).collect();
+    let content = match serde_json::to_string_pretty(&report) {
+        Ok(content) => content,
+        Err(e) => {
+            log_error!("Failed to write the report: {}", e);
+            return false;
+        }
+    };
+    match fs::write(path, content + "\n") {
+        Ok(_) => {
+            log_info!("the report has been written to: {}", path);
+            true
+        }
+        Err(e) => {
+            log_error!("Failed to write to file: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer_types() {
+        assert_eq!(infer_type(&["1", "", "-3"]), ("integer", 0));
+        assert_eq!(infer_type(&["1", "2.5", "x"]), ("number", 1));
+        assert_eq!(infer_type(&["true", "false", ""]), ("boolean", 0));
+        assert_eq!(infer_type(&["1", "bob"]), ("text", 0));
+        assert_eq!(infer_type(&["", ""]), ("empty", 0));
+    }
+
+    #[test]
+    fn numeric_column() {
+        let profile = ColumnProfile::new("age", &["30", "25", "", "41", "25", "n/a"], 2);
+        assert_eq!((profile.rows, profile.empty, profile.distinct), (6, 1, 4));
+        assert_eq!((profile.kind, profile.invalid), ("integer", 1));
+        assert_eq!(profile.min.as_deref(), Some("25"));
+        assert_eq!(profile.max.as_deref(), Some("41"));
+        assert_eq!(profile.mean, Some(30.25));
+        assert_eq!(profile.median, Some(27.5));
+        assert_eq!(
+            profile.top,
+            vec![("25".to_string(), 2), ("30".to_string(), 1)]
+        );
+        assert_eq!((profile.min_length, profile.max_length), (2, 3));
+    }
+
+    #[test]
+    fn text_column() {
+        let profile = ColumnProfile::new("name", &["bob", "jack", "ann"], 5);
+        assert_eq!(profile.kind, "text");
+        assert_eq!(profile.min.as_deref(), Some("ann"));
+        assert_eq!(profile.mean, None);
+        assert_eq!((profile.min_length, profile.max_length), (3, 4));
+    }
+
+    #[test]
+    fn parse_options() {
+        let profile = Profile::parse(&["$age", "top", "3", "to", "\"r.json\""]).unwrap();
+        assert_eq!(profile.fields, vec!["age"]);
+        assert_eq!(profile.top, 3);
+        assert_eq!(profile.path.as_deref(), Some("r.json"));
+        assert!(Profile::parse(&["to", "\"r.csv\""]).is_none());
+        assert!(Profile::parse(&["top", "x"]).is_none());
+    }
+}
@@ -14,16 +14,24 @@ pub enum AggregationFunction {
     Count,
     Counter,
 }
+// the functions looked up by the parser, the help and the tab completion
+const AGGREGATION_FUNCTIONS: [(&str, AggregationFunction); 5] = [
+    ("sum", AggregationFunction::Sum),
+    ("avg", AggregationFunction::Avg),
+    ("mean", AggregationFunction::Mean),
+    ("count", AggregationFunction::Count),
+    ("counter", AggregationFunction::Counter),
+];
 impl AggregationFunction {
+    pub fn get_available_functions_names() -> Vec<&'static str> {
+        AGGREGATION_FUNCTIONS.iter().map(|(name, _)| *name).collect()
+    }
+
     pub fn get_function_from_name(name: &str) -> Option<AggregationFunction> {
-        match name {
-            "sum" => return Some(AggregationFunction::Sum),
-            "avg" => return Some(AggregationFunction::Avg),
-            "mean" => return Some(AggregationFunction::Mean),
-            "count" => return Some(AggregationFunction::Count),
-            "counter" => return Some(AggregationFunction::Counter),
-            _ => None,
-        }
+        AGGREGATION_FUNCTIONS
+            .into_iter()
+            .find(|(function, _)| *function == name)
+            .map(|(_, function)| function)
     }
 }
 #[derive(Debug)]
@@ -1,5 +1,6 @@
 use super::ParseResult;
 use super::assignment::Assignment;
+use super::value;
 #[derive(Debug)]
 pub struct AssignList {
     assignments: Vec<Assignment>,
@@ -24,26 +25,39 @@ impl AssignList {
             }
         }
     }
-    pub fn set_evaluation(&self, fields: &Vec<String>, row: &mut Vec<String>) {
-        for assignment in self.assignments.iter() {
-            assignment.evaluate(fields, row);
-        }
+    // the evaluations stop at the first assignment that fails and return false
+    pub fn set_evaluation(&self, fields: &Vec<String>, row: &mut Vec<String>) -> bool {
+        self.assignments
+            .iter()
+            .all(|assignment| assignment.evaluate(fields, row))
+    }
+    // the indexes of the assigned fields, the unknown fields are left out
+    pub fn field_indices(&self, fields: &[String]) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .assignments
+            .iter()
+            .filter_map(|assignment| value::get_field_idx(fields, assignment.field_name()))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
     }
     pub fn insert_column_evaluation(
         &self,
         fields: &mut Vec<String>,
         rows: &mut Vec<Vec<String>>,
         where_clause_eval_results: &Vec<bool>,
-    ) {
-        for assignment in self.assignments.iter() {
-            assignment.insert_column_evaluation(fields, rows, where_clause_eval_results);
-        }
+    ) -> bool {
+        self.assignments.iter().all(|assignment| {
+            assignment.insert_column_evaluation(fields, rows, where_clause_eval_results)
+        })
     }
-    pub fn insert_row_evaluation(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
+    pub fn insert_row_evaluation(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> bool {
         let mut new_row: Vec<String> = vec!["".to_string(); fields.len()];
-        for assignment in self.assignments.iter() {
-            assignment.evaluate(fields, &mut new_row);
+        if !self.set_evaluation(fields, &mut new_row) {
+            return false;
         }
         rows.push(new_row);
+        true
     }
 }
@@ -53,26 +53,34 @@ impl Assignment {
             None => return (ParseResult::None, idx),
         }
     }
-    pub fn evaluate(&self, fields: &Vec<String>, row: &mut Vec<String>) {
-        match fields.iter().position(|f| *f == self.field_name) {
+    pub fn field_name(&self) -> &str {
+        &self.field_name
+    }
+    // returns false if the value could not be computed
+    pub fn evaluate(&self, fields: &Vec<String>, row: &mut Vec<String>) -> bool {
+        match value::get_field_idx(fields, &self.field_name) {
             Some(idx) => match self.modification.evaluate(fields, row) {
                 Some(new_val) => row[idx] = new_val,
-                None => {}
+                None => return false,
             },
             None => {
                 log_error!("no field named {}", self.field_name);
+                return false;
             }
         }
+        true
     }
     pub fn insert_column_evaluation(
         &self,
         fields: &mut Vec<String>,
         rows: &mut Vec<Vec<String>>,
         where_clause_eval_results: &Vec<bool>,
-    ) {
+    ) -> bool {
+        let mut succeeded = true;
         match fields.iter().position(|f| *f == self.field_name) {
             Some(_) => {
                 log_error!("the field name '{}' is already exist", self.field_name);
+                return false;
             }
             None => {
                 fields.push(self.field_name.clone());
@@ -82,7 +90,11 @@ impl Assignment {
                     if where_clause_eval_results[i] {
                         match self.modification.evaluate(fields, &rows[i]) {
                             Some(new_val) => rows[i].push(new_val),
-                            None => rows[i].push("".to_string()),
+                            None => {
+                                // the row still gets a value so every row keeps the same length
+                                rows[i].push("".to_string());
+                                succeeded = false;
+                            }
                         }
                     } else {
                         rows[i].push("".to_string());
@@ -90,5 +102,6 @@ impl Assignment {
                 }
             }
         }
+        succeeded
     }
 }
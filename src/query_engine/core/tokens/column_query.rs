@@ -0,0 +1,291 @@
+use super::ParseResult;
+use super::get_query::GetQuery;
+use super::value;
+use crate::history::ColumnsChange;
+use crate::log_error;
+
+#[derive(Debug, PartialEq)]
+pub enum Position {
+    Before,
+    After,
+}
+
+// the commands that change the columns of the table without touching the values
+#[derive(Debug, PartialEq)]
+pub enum ColumnQuery {
+    Drop(Vec<String>),
+    Rename(String, String),
+    Move(String, Position, String),
+    Select(Vec<String>),
+    Copy(String, String),
+}
+
+impl ColumnQuery {
+    pub fn parse(lexemes: &[String]) -> ParseResult<Self> {
+        let usage = match lexemes.first().map(|l| l.as_str()) {
+            Some("drop-column") => "drop-column $field ($field)*",
+            Some("rename-column") => "rename-column $old to $new",
+            Some("move-column") => "move-column $field (after | before) $field",
+            Some("select-columns") => "select-columns $field ($field)*",
+            Some("copy-column") => "copy-column $field to $new",
+            _ => return ParseResult::None,
+        };
+        let query = match (lexemes[0].as_str(), &lexemes[1..]) {
+            ("drop-column", _) => Self::parse_fields(lexemes).map(ColumnQuery::Drop),
+            ("select-columns", _) => Self::parse_fields(lexemes).map(ColumnQuery::Select),
+            ("rename-column", [old, to, new]) if to == "to" => {
+                Self::parse_pair(old, new).map(|(old, new)| ColumnQuery::Rename(old, new))
+            }
+            ("copy-column", [field, to, new]) if to == "to" => {
+                Self::parse_pair(field, new).map(|(field, new)| ColumnQuery::Copy(field, new))
+            }
+            ("move-column", [field, position, target]) => {
+                let position = match position.as_str() {
+                    "after" => Some(Position::After),
+                    "before" => Some(Position::Before),
+                    _ => None,
+                };
+                match (position, Self::parse_pair(field, target)) {
+                    (Some(position), Some((field, target))) => {
+                        Some(ColumnQuery::Move(field, position, target))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match query {
+            Some(query) => ParseResult::Val(query),
+            None => {
+                log_error!("usage: {}", usage);
+                ParseResult::Err
+            }
+        }
+    }
+
+    // a list of field names that ends the query
+    fn parse_fields(lexemes: &[String]) -> Option<Vec<String>> {
+        match GetQuery::parse_field_name_list(lexemes, 1) {
+            Some((names, last_idx)) if last_idx == lexemes.len() - 1 => Some(names),
+            _ => None,
+        }
+    }
+
+    fn parse_pair(lhs: &String, rhs: &String) -> Option<(String, String)> {
+        Some((value::parse_field_name(lhs)?, value::parse_field_name(rhs)?))
+    }
+
+    // applies the command to the fields and every row, returns the change or None if the command
+    // failed
+    pub fn evaluate(
+        &self,
+        fields: &mut Vec<String>,
+        rows: &mut [Vec<String>],
+    ) -> Option<ColumnsChange> {
+        // the new columns are described by the index of the column they take their values from
+        let mut names: Vec<String> = fields.clone();
+        let layout: Vec<usize> = match self {
+            ColumnQuery::Drop(names_to_drop) => {
+                let idxs = Self::get_idxs(fields, names_to_drop)?;
+                let kept: Vec<usize> = (0..fields.len()).filter(|i| !idxs.contains(i)).collect();
+                if kept.is_empty() {
+                    log_error!("a table should keep at least one column");
+                    return None;
+                }
+                names = kept.iter().map(|&i| fields[i].clone()).collect();
+                kept
+            }
+            ColumnQuery::Select(names_to_keep) => {
+                let idxs = Self::get_idxs(fields, names_to_keep)?;
+                names = idxs.iter().map(|&i| fields[i].clone()).collect();
+                idxs
+            }
+            ColumnQuery::Rename(old, new) => {
+                let idx = Self::get_idx(fields, old)?;
+                if !Self::is_new_name(fields, new) {
+                    return None;
+                }
+                names[idx] = new.clone();
+                (0..fields.len()).collect()
+            }
+            ColumnQuery::Copy(field, new) => {
+                let idx = Self::get_idx(fields, field)?;
+                if !Self::is_new_name(fields, new) {
+                    return None;
+                }
+                names.push(new.clone());
+                (0..fields.len()).chain(std::iter::once(idx)).collect()
+            }
+            ColumnQuery::Move(field, position, target) => {
+                let (Some(idx), Some(target_idx)) =
+                    (Self::get_idx(fields, field), Self::get_idx(fields, target))
+                else {
+                    return None;
+                };
+                if idx == target_idx {
+                    log_error!("a column can not be moved next to itself");
+                    return None;
+                }
+                let mut layout: Vec<usize> = (0..fields.len()).filter(|&i| i != idx).collect();
+                let target_position = layout.iter().position(|&i| i == target_idx).unwrap();
+                match position {
+                    Position::Before => layout.insert(target_position, idx),
+                    Position::After => layout.insert(target_position + 1, idx),
+                }
+                names = layout.iter().map(|&i| fields[i].clone()).collect();
+                layout
+            }
+        };
+        let change = ColumnsChange::new(fields, rows, names, &layout);
+        for row in rows.iter_mut() {
+            *row = layout.iter().map(|&i| row[i].clone()).collect();
+        }
+        *fields = change.new_fields().clone();
+        Some(change)
+    }
+
+    fn get_idx(fields: &[String], field_name: &str) -> Option<usize> {
+        let idx = value::get_field_idx(fields, field_name);
+        if idx.is_none() {
+            log_error!("no field named {}", field_name);
+        }
+        idx
+    }
+
+    fn get_idxs(fields: &[String], fields_names: &[String]) -> Option<Vec<usize>> {
+        let mut idxs: Vec<usize> = Vec::new();
+        for field_name in fields_names.iter() {
+            let idx = Self::get_idx(fields, field_name)?;
+            if idxs.contains(&idx) {
+                log_error!("the field {} is given more than once", field_name);
+                return None;
+            }
+            idxs.push(idx);
+        }
+        Some(idxs)
+    }
+
+    fn is_new_name(fields: &[String], name: &str) -> bool {
+        if fields.iter().any(|f| f == name) {
+            log_error!("the field name '{}' is already exist", name);
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_data() -> (Vec<String>, Vec<Vec<String>>) {
+        let fields = ["id", "name", "age"].map(|f| f.to_string()).to_vec();
+        let rows = vec![
+            ["1", "bob", "30"].map(|v| v.to_string()).to_vec(),
+            ["2", "jack", "25"].map(|v| v.to_string()).to_vec(),
+        ];
+        (fields, rows)
+    }
+
+    fn parse(query: &str) -> ColumnQuery {
+        let lexemes: Vec<String> = query.split(' ').map(|l| l.to_string()).collect();
+        match ColumnQuery::parse(&lexemes) {
+            ParseResult::Val(query) => query,
+            _ => panic!("{query} should be parsed"),
+        }
+    }
+
+    #[test]
+    fn drop_column() {
+        let (mut fields, mut rows) = get_data();
+        assert!(
+            parse("drop-column $name")
+                .evaluate(&mut fields, &mut rows)
+                .is_some()
+        );
+        assert_eq!(fields, vec!["id", "age"]);
+        assert_eq!(rows[1], vec!["2", "25"]);
+        // the last columns can not be dropped
+        assert!(
+            parse("drop-column $id $age")
+                .evaluate(&mut fields, &mut rows)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn rename_column() {
+        let (mut fields, mut rows) = get_data();
+        assert!(
+            parse("rename-column $name to $first_name")
+                .evaluate(&mut fields, &mut rows)
+                .is_some()
+        );
+        assert_eq!(fields, vec!["id", "first_name", "age"]);
+        assert!(
+            parse("rename-column $id to $age")
+                .evaluate(&mut fields, &mut rows)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn move_column() {
+        let (mut fields, mut rows) = get_data();
+        assert!(
+            parse("move-column $age before $id")
+                .evaluate(&mut fields, &mut rows)
+                .is_some()
+        );
+        assert_eq!(fields, vec!["age", "id", "name"]);
+        assert_eq!(rows[0], vec!["30", "1", "bob"]);
+        assert!(
+            parse("move-column $age after $name")
+                .evaluate(&mut fields, &mut rows)
+                .is_some()
+        );
+        assert_eq!(fields, vec!["id", "name", "age"]);
+        assert_eq!(rows[0], vec!["1", "bob", "30"]);
+    }
+
+    #[test]
+    fn select_columns() {
+        let (mut fields, mut rows) = get_data();
+        assert!(
+            parse("select-columns $age $id")
+                .evaluate(&mut fields, &mut rows)
+                .is_some()
+        );
+        assert_eq!(fields, vec!["age", "id"]);
+        assert_eq!(rows[1], vec!["25", "2"]);
+        assert!(
+            parse("select-columns $id $id")
+                .evaluate(&mut fields, &mut rows)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn copy_column() {
+        let (mut fields, mut rows) = get_data();
+        assert!(
+            parse("copy-column $name to $alias")
+                .evaluate(&mut fields, &mut rows)
+                .is_some()
+        );
+        assert_eq!(fields, vec!["id", "name", "age", "alias"]);
+        assert_eq!(rows[0], vec!["1", "bob", "30", "bob"]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let lexemes: Vec<String> = ["rename-column", "$a", "$b"]
+            .map(|l| l.to_string())
+            .to_vec();
+        assert!(matches!(ColumnQuery::parse(&lexemes), ParseResult::Err));
+        let lexemes: Vec<String> = ["move-column", "$a", "next", "$b"]
+            .map(|l| l.to_string())
+            .to_vec();
+        assert!(matches!(ColumnQuery::parse(&lexemes), ParseResult::Err));
+    }
+}
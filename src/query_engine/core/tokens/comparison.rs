@@ -40,6 +40,35 @@ impl Display for ComparisonOps {
         Ok(())
     }
 }
+// the operators looked up by the parser, the help and the tab completion, the values of between
+// are parsed after it
+const OPERATORS: [(&str, ComparisonOps); 13] = [
+    ("==", ComparisonOps::Equal),
+    ("!=", ComparisonOps::NotEqual),
+    ("<", ComparisonOps::LessThan),
+    (">", ComparisonOps::GreaterThan),
+    ("<=", ComparisonOps::LessThanOrEqual),
+    (">=", ComparisonOps::GreaterThanOrEqual),
+    ("between", ComparisonOps::BetweenOp(Value::None, Value::None)),
+    ("is", ComparisonOps::Is),
+    ("isnot", ComparisonOps::IsNot),
+    ("contains", ComparisonOps::Contains),
+    ("starts-with", ComparisonOps::StartsWith),
+    ("ends-with", ComparisonOps::EndsWith),
+    ("in", ComparisonOps::In),
+];
+impl ComparisonOps {
+    pub fn get_available_operators_names() -> Vec<&'static str> {
+        OPERATORS.iter().map(|(name, _)| *name).collect()
+    }
+
+    fn from_lexeme(lexeme: &str) -> Option<Self> {
+        OPERATORS
+            .into_iter()
+            .find(|(name, _)| *name == lexeme)
+            .map(|(_, op)| op)
+    }
+}
 #[derive(Debug)]
 pub struct Comparison {
     field_name: String,
@@ -59,14 +88,8 @@ impl Comparison {
                     let rhs: Value;
 
                     // the second lexeme must be a comparison operator
-                    match lexeme.as_str() {
-                        "==" => comparison_op = ComparisonOps::Equal,
-                        "!=" => comparison_op = ComparisonOps::NotEqual,
-                        ">=" => comparison_op = ComparisonOps::GreaterThanOrEqual,
-                        "<=" => comparison_op = ComparisonOps::LessThanOrEqual,
-                        ">" => comparison_op = ComparisonOps::GreaterThan,
-                        "<" => comparison_op = ComparisonOps::LessThan,
-                        "between" => {
+                    match ComparisonOps::from_lexeme(lexeme) {
+                        Some(ComparisonOps::BetweenOp(_, _)) => {
                             let val1: Value;
                             let val2: Value;
                             // the first parameter for the between operator
@@ -130,13 +153,8 @@ impl Comparison {
                                 return (ParseResult::Err, idx - 1);
                             }
                         }
-                        "is" => comparison_op = ComparisonOps::Is,
-                        "isnot" => comparison_op = ComparisonOps::IsNot,
-                        "contains" => comparison_op = ComparisonOps::Contains,
-                        "starts-with" => comparison_op = ComparisonOps::StartsWith,
-                        "ends-with" => comparison_op = ComparisonOps::EndsWith,
-                        "in" => comparison_op = ComparisonOps::In,
-                        _ => {
+                        Some(op) => comparison_op = op,
+                        None => {
                             log_error!("expecting a comparison operator after the field name");
                             return (ParseResult::Err, idx);
                         }
@@ -222,7 +240,7 @@ impl Comparison {
                 return self.compair_strings(fields, row, |a, b| return a.ends_with(b));
             }
             ComparisonOps::In => match &self.rhs {
-                Value::List(list) => match fields.iter().position(|f| *f == self.field_name) {
+                Value::List(list) => match value::get_field_idx(fields, &self.field_name) {
                     Some(idx) => return list.contains(&row[idx]),
                     None => {
                         log_error!("no field named {}", self.field_name);
@@ -302,7 +320,7 @@ impl Comparison {
     }
 
     fn is_none(&self, fields: &Vec<String>, row: &Vec<String>) -> bool {
-        match fields.iter().position(|f| *f == self.field_name) {
+        match value::get_field_idx(fields, &self.field_name) {
             Some(idx) => return row[idx] == "",
             None => {
                 log_error!("no field named {}", self.field_name);
@@ -319,14 +337,14 @@ impl Comparison {
     ) -> Option<Ordering> {
         let lhs_idx: usize;
         let rhs_idx: usize;
-        match fields.iter().position(|f| *f == self.field_name) {
+        match value::get_field_idx(fields, &self.field_name) {
             Some(idx) => lhs_idx = idx,
             None => {
                 log_error!("no field named {}", self.field_name);
                 return None;
             }
         }
-        match fields.iter().position(|f| f == field) {
+        match value::get_field_idx(fields, field) {
             Some(idx) => rhs_idx = idx,
             None => {
                 log_error!("no field named {}", self.field_name);
@@ -366,7 +384,7 @@ impl Comparison {
         if self.is_none(fields, row) {
             return Some(Ordering::Less);
         }
-        match fields.iter().position(|f| *f == self.field_name) {
+        match value::get_field_idx(fields, &self.field_name) {
             Some(idx) => field_idx = idx,
             None => {
                 log_error!("no field named {}", self.field_name);
@@ -399,7 +417,7 @@ impl Comparison {
         F: Fn(&String, &String) -> bool,
     {
         match &self.rhs {
-            Value::Literal(val) => match fields.iter().position(|f| *f == self.field_name) {
+            Value::Literal(val) => match value::get_field_idx(fields, &self.field_name) {
                 Some(idx) => return comparison(&row[idx], &val.to_string()),
                 None => {
                     log_error!("no field named {}", self.field_name);
@@ -407,8 +425,8 @@ impl Comparison {
                 }
             },
             Value::FieldName(field_name) => {
-                match fields.iter().position(|f| *f == self.field_name) {
-                    Some(idx1) => match fields.iter().position(|f| *f == *field_name) {
+                match value::get_field_idx(fields, &self.field_name) {
+                    Some(idx1) => match value::get_field_idx(fields, field_name) {
                         Some(idx2) => {
                             return comparison(&row[idx1], &row[idx2]);
                         }
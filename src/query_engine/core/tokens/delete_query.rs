@@ -39,7 +39,31 @@ impl DeleteQuery {
         }
     }
 
-    pub fn evaluate(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> () {
-      rows.retain(|row| !self.where_clause.evaluate(fields, row));
+    // the removed rows are added to removed with their index
+    pub fn evaluate(
+        &self,
+        fields: &mut Vec<String>,
+        rows: &mut Vec<Vec<String>>,
+        removed: &mut Vec<(usize, Vec<String>)>,
+    ) -> bool {
+        let mut kept: Vec<Vec<String>> = Vec::with_capacity(rows.len());
+        for (i, row) in std::mem::take(rows).into_iter().enumerate() {
+            if self.where_clause.evaluate(fields, &row) {
+                removed.push((i, row));
+            } else {
+                kept.push(row);
+            }
+        }
+        *rows = kept;
+        true
+    }
+
+    // the rows that satisfy the where clause with their index, the rows are not modified
+    pub fn preview(&self, fields: &Vec<String>, rows: &[Vec<String>]) -> Vec<(usize, Vec<String>)> {
+        rows.iter()
+            .enumerate()
+            .filter(|(_, row)| self.where_clause.evaluate(fields, row))
+            .map(|(i, row)| (i, row.clone()))
+            .collect()
     }
 }
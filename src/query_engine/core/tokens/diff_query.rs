@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+use serde_json::{Map, Value, json};
+
+use super::ParseResult;
+use super::get_query::GetQuery;
+use super::value;
+use crate::csv_parser::{self, Dialect};
+use crate::log_error;
+use crate::log_info;
+use crate::log_warning;
+use crate::OPTIONS;
+
+// compares two csv files row by row, the rows are matched by the value of a key field
+// (ex: diff "old.csv" "new.csv" key $id to "patch.json")
+#[derive(Debug)]
+pub struct DiffQuery {
+    old_path: String,
+    new_path: String,
+    key: String,
+    patch_path: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct CellChange {
+    key: String,
+    field: String,
+    old: String,
+    new: String,
+}
+
+#[derive(Debug)]
+struct Diff {
+    key: String,
+    old_fields: Vec<String>,
+    new_fields: Vec<String>,
+    added: Vec<Vec<String>>,
+    removed: Vec<Vec<String>>,
+    changed: Vec<CellChange>,
+}
+
+impl DiffQuery {
+    pub fn parse(lexemes: &[String]) -> ParseResult<Self> {
+        match lexemes.first() {
+            Some(lexeme) if lexeme == "diff" => {}
+            _ => return ParseResult::None,
+        }
+        let usage = "usage: diff \"old.csv\" \"new.csv\" key $field (to \"patch.csv\")?";
+        let old_path = lexemes.get(1).and_then(value::parse_literal);
+        let new_path = lexemes.get(2).and_then(value::parse_literal);
+        let key = match lexemes.get(3..5) {
+            Some([key_word, field]) if key_word == "key" => value::parse_field_name(field),
+            _ => None,
+        };
+        let (Some(old_path), Some(new_path), Some(key)) = (old_path, new_path, key) else {
+            log_error!("{}", usage);
+            return ParseResult::Err;
+        };
+        let patch_path = match lexemes.get(5..) {
+            Some([]) | None => None,
+            Some([to, path]) if to == "to" => match value::parse_literal(path) {
+                Some(path) if path.ends_with(".csv") || path.ends_with(".json") => Some(path),
+                _ => {
+                    log_error!("the patch should be written to a .csv or a .json file");
+                    return ParseResult::Err;
+                }
+            },
+            Some(_) => {
+                log_error!("{}", usage);
+                return ParseResult::Err;
+            }
+        };
+        ParseResult::Val(DiffQuery {
+            old_path,
+            new_path,
+            key,
+            patch_path,
+        })
+    }
+
+    pub fn evaluate(&self) -> bool {
+        let options = OPTIONS.get().unwrap();
+        let old_dialect = Dialect::of_file(&self.old_path, None);
+        let Some(old) = csv_parser::parse_file(&self.old_path, &old_dialect, None, options) else {
+            return false;
+        };
+        let new_dialect = Dialect::of_file(&self.new_path, None);
+        let Some(new) = csv_parser::parse_file(&self.new_path, &new_dialect, None, options) else {
+            return false;
+        };
+        let Some(diff) = Diff::new(&self.key, old.fields, old.rows, new.fields, new.rows) else {
+            return false;
+        };
+        diff.print();
+        if let Some(patch_path) = &self.patch_path {
+            return diff.write_patch(patch_path);
+        }
+        true
+    }
+}
+
+impl Diff {
+    fn new(
+        key: &str,
+        old_fields: Vec<String>,
+        old_rows: Vec<Vec<String>>,
+        new_fields: Vec<String>,
+        new_rows: Vec<Vec<String>>,
+    ) -> Option<Self> {
+        let (Some(old_key_idx), Some(new_key_idx)) = (
+            value::get_field_idx(&old_fields, key),
+            value::get_field_idx(&new_fields, key),
+        ) else {
+            log_error!("both files should have a field named {}", key);
+            return None;
+        };
+        for field in new_fields.iter().filter(|f| !old_fields.contains(f)) {
+            log_info!("the field {} was added", field);
+        }
+        for field in old_fields.iter().filter(|f| !new_fields.contains(f)) {
+            log_info!("the field {} was removed", field);
+        }
+        // the fields that are in both files, with their index in the old and the new rows
+        let common: Vec<(usize, usize)> = old_fields
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| new_fields.iter().position(|n| n == f).map(|j| (i, j)))
+            .collect();
+
+        let old_index = Self::index(&old_rows, old_key_idx, "old");
+        let new_index = Self::index(&new_rows, new_key_idx, "new");
+
+        let mut changed: Vec<CellChange> = Vec::new();
+        let mut removed: Vec<Vec<String>> = Vec::new();
+        for (i, old_row) in old_rows.iter().enumerate() {
+            let old_key = &old_row[old_key_idx];
+            // only the first row of a repeated key is compared
+            if old_index.get(old_key) != Some(&i) {
+                continue;
+            }
+            let Some(&j) = new_index.get(old_key) else {
+                removed.push(old_row.clone());
+                continue;
+            };
+            for &(old_idx, new_idx) in common.iter() {
+                if old_row[old_idx] != new_rows[j][new_idx] {
+                    changed.push(CellChange {
+                        key: old_key.clone(),
+                        field: old_fields[old_idx].clone(),
+                        old: old_row[old_idx].clone(),
+                        new: new_rows[j][new_idx].clone(),
+                    });
+                }
+            }
+        }
+        let added: Vec<Vec<String>> = new_rows
+            .iter()
+            .enumerate()
+            .filter(|(j, row)| {
+                let new_key = &row[new_key_idx];
+                new_index.get(new_key) == Some(j) && !old_index.contains_key(new_key)
+            })
+            .map(|(_, row)| row.clone())
+            .collect();
+
+        Some(Diff {
+            key: key.to_string(),
+            old_fields,
+            new_fields,
+            added,
+            removed,
+            changed,
+        })
+    }
+
+    // maps every key to the index of its first row
+    fn index<'a>(
+        rows: &'a [Vec<String>],
+        key_idx: usize,
+        file: &str,
+    ) -> HashMap<&'a String, usize> {
+        let mut index: HashMap<&String, usize> = HashMap::new();
+        let mut repeated = 0;
+        for (i, row) in rows.iter().enumerate() {
+            if index.contains_key(&row[key_idx]) {
+                repeated += 1;
+            } else {
+                index.insert(&row[key_idx], i);
+            }
+        }
+        if repeated > 0 {
+            log_warning!(
+                "{} rows of the {} file repeat a key, only the first row of each key is compared",
+                repeated,
+                file
+            );
+        }
+        index
+    }
+
+    fn print(&self) {
+        if !self.added.is_empty() {
+            log_info!("added rows:");
+            GetQuery::print_table(&self.new_fields, &self.added.iter().collect());
+        }
+        if !self.removed.is_empty() {
+            log_info!("removed rows:");
+            GetQuery::print_table(&self.old_fields, &self.removed.iter().collect());
+        }
+        if !self.changed.is_empty() {
+            log_info!("changed cells:");
+            let fields = vec![
+                self.key.clone(),
+                "field".to_string(),
+                "old → new".to_string(),
+            ];
+            let rows: Vec<Vec<String>> = self
+                .changed
+                .iter()
+                .map(|c| {
+                    let change = format!("{} → {}", c.old, c.new);
+                    vec![c.key.clone(), c.field.clone(), change]
+                })
+                .collect();
+            GetQuery::print_table(&fields, &rows.iter().collect());
+        }
+        log_info!(
+            "{} added rows, {} removed rows, {} changed cells",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+    }
+
+    // the patch has one line per changed value: change, key, field, old value, new value, the
+    // added and removed rows have a line for each of their fields
+    fn patch_rows(&self) -> Vec<Vec<String>> {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let key_of = |fields: &[String], row: &[String]| {
+            value::get_field_idx(fields, &self.key).map_or(String::new(), |i| row[i].clone())
+        };
+        for row in self.added.iter() {
+            let key = key_of(&self.new_fields, row);
+            for (field, val) in self.new_fields.iter().zip(row.iter()) {
+                let change = ["added", &key, field, "", val];
+                rows.push(change.iter().map(|v| v.to_string()).collect());
+            }
+        }
+        for row in self.removed.iter() {
+            let key = key_of(&self.old_fields, row);
+            for (field, val) in self.old_fields.iter().zip(row.iter()) {
+                let change = ["removed", &key, field, val, ""];
+                rows.push(change.iter().map(|v| v.to_string()).collect());
+            }
+        }
+        for c in self.changed.iter() {
+            let change = ["changed", &c.key, &c.field, &c.old, &c.new];
+            rows.push(change.iter().map(|v| v.to_string()).collect());
+        }
+        rows
+    }
+
+    fn to_json(&self) -> Value {
+        let to_object = |fields: &[String], row: &[String]| {
+            let object: Map<String, Value> = fields
+                .iter()
+                .zip(row.iter())
+                .map(|(f, v)| (f.clone(), Value::String(v.clone())))
+                .collect();
+            Value::Object(object)
+        };
+        json!({
+            "key": self.key,
+            "added": self.added.iter().map(|row| to_object(&self.new_fields, row)).collect::<Vec<Value>>(),
+            "removed": self.removed.iter().map(|row| to_object(&self.old_fields, row)).collect::<Vec<Value>>(),
+            "changed": self.changed.iter().map(|c| json!({
+                "key": c.key,
+                "field": c.field,
+                "old": c.old,
+                "new": c.new,
+            })).collect::<Vec<Value>>(),
+        })
+    }
+
+    fn write_patch(&self, path: &str) -> bool {
+        let content = if path.ends_with(".json") {
+            match serde_json::to_string_pretty(&self.to_json()) {
+                Ok(content) => content,
+                Err(e) => {
+                    log_error!("Failed to write the patch: {}", e);
+                    return false;
+                }
+            }
+        } else {
+            let header = ["change", &self.key, "field", "old", "new"].map(|v| v.to_string());
+            std::iter::once(header.to_vec())
+                .chain(self.patch_rows())
+                .map(|row| {
+                    row.iter()
+                        .map(|v| quote(v))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+        let mut file = match File::create(path) {
+            Ok(file) => file,
+            Err(e) => {
+                log_error!("Failed to create file: {}", e);
+                return false;
+            }
+        };
+        match writeln!(file, "{}", content) {
+            Ok(_) => {
+                log_info!("the patch has been written to: {}", path);
+                true
+            }
+            Err(e) => {
+                log_error!("Failed to write to file: {}", e);
+                false
+            }
+        }
+    }
+}
+
+// puts a value of the csv patch between quotes when it contains a comma, a quote or a new line
+fn quote(val: &str) -> String {
+    if val.contains([',', '"', '\n']) {
+        format!("\"{}\"", val.replace('"', "\"\""))
+    } else {
+        val.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|v| v.to_string()).collect())
+            .collect()
+    }
+
+    fn get_diff() -> Diff {
+        let old_fields = vec!["id".to_string(), "name".to_string(), "age".to_string()];
+        let old_rows = to_rows(&[
+            &["1", "bob", "30"],
+            &["2", "jack", "25"],
+            &["3", "ann", "41"],
+        ]);
+        // the fields can be in an other order in the new file
+        let new_fields = vec!["name".to_string(), "id".to_string(), "age".to_string()];
+        let new_rows = to_rows(&[
+            &["bob", "1", "31"],
+            &["ann", "3", "41"],
+            &["zed", "4", "19"],
+        ]);
+        Diff::new("id", old_fields, old_rows, new_fields, new_rows).unwrap()
+    }
+
+    #[test]
+    fn added_removed_and_changed() {
+        let diff = get_diff();
+        assert_eq!(diff.added, to_rows(&[&["zed", "4", "19"]]));
+        assert_eq!(diff.removed, to_rows(&[&["2", "jack", "25"]]));
+        assert_eq!(
+            diff.changed,
+            vec![CellChange {
+                key: "1".to_string(),
+                field: "age".to_string(),
+                old: "30".to_string(),
+                new: "31".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn patch_rows() {
+        let rows = get_diff().patch_rows();
+        assert_eq!(rows.len(), 7);
+        assert_eq!(rows[0], vec!["added", "4", "name", "", "zed"]);
+        assert_eq!(rows[3], vec!["removed", "2", "id", "2", ""]);
+        assert_eq!(rows[6], vec!["changed", "1", "age", "30", "31"]);
+    }
+
+    #[test]
+    fn json_patch() {
+        let patch = get_diff().to_json();
+        assert_eq!(patch["added"][0]["name"], "zed");
+        assert_eq!(patch["removed"][0]["id"], "2");
+        assert_eq!(patch["changed"][0]["new"], "31");
+    }
+
+    #[test]
+    fn missing_key() {
+        let fields = vec!["id".to_string()];
+        let other = vec!["name".to_string()];
+        assert!(Diff::new("id", fields, vec![], other, vec![]).is_none());
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use super::ParseResult;
+use super::get_query::GetQuery;
+use super::value;
+use super::where_clause::WhereClause;
+use crate::log_error;
+use crate::log_info;
+
+#[derive(Debug)]
+pub struct DuplicatesQuery {
+    fields_names: Vec<String>,
+    where_clause: Option<WhereClause>,
+}
+
+impl DuplicatesQuery {
+    pub fn parse(lexemes: &[String]) -> ParseResult<Self> {
+        if let Some(lexeme) = lexemes.first() {
+            if *lexeme != "duplicates" {
+                return ParseResult::None;
+            }
+        } else {
+            return ParseResult::None;
+        }
+        // the key of the rows is a list of fields names (ex: duplicates $first_name $last_name)
+        let fields_names: Vec<String>;
+        let last_idx: usize;
+        match GetQuery::parse_field_name_list(lexemes, 1) {
+            Some((names, idx)) => {
+                fields_names = names;
+                last_idx = idx;
+            }
+            None => {
+                log_error!("expecting at least one field name after the duplicates key word");
+                return ParseResult::Err;
+            }
+        }
+        let (where_clause_parse_result, last_idx) = WhereClause::parse(lexemes, last_idx + 1);
+        let where_clause = match where_clause_parse_result {
+            ParseResult::Val(where_clause) => Some(where_clause),
+            ParseResult::None => None,
+            ParseResult::Err => return ParseResult::Err,
+        };
+        if let Some(lexeme) = lexemes.get(last_idx + 1) {
+            log_error!("unexpected '{}' after the duplicates command", lexeme);
+            return ParseResult::Err;
+        }
+        ParseResult::Val(DuplicatesQuery {
+            fields_names,
+            where_clause,
+        })
+    }
+
+    pub fn evaluate(&self, fields: &Vec<String>, rows: &[Vec<String>]) -> bool {
+        let mut key_idxs: Vec<usize> = Vec::new();
+        for field_name in self.fields_names.iter() {
+            match value::get_field_idx(fields, field_name) {
+                Some(idx) => key_idxs.push(idx),
+                None => {
+                    log_error!("no field named {}", field_name);
+                    return false;
+                }
+            }
+        }
+        let valid_rows: Vec<&Vec<String>> = match &self.where_clause {
+            Some(where_clause) => rows
+                .iter()
+                .filter(|row| where_clause.evaluate(fields, row))
+                .collect(),
+            None => rows.iter().collect(),
+        };
+
+        let groups = Self::group_duplicates(&key_idxs, &valid_rows);
+        if groups.is_empty() {
+            log_info!("no duplicated key was found");
+            return true;
+        }
+        // every duplicated row is printed followed by the number of times its key occurs
+        let mut result_fields = fields.clone();
+        result_fields.push("count".to_string());
+        let mut result_rows: Vec<Vec<String>> = Vec::new();
+        for group in groups.iter() {
+            for &i in group.iter() {
+                let mut row = valid_rows[i].clone();
+                row.push(group.len().to_string());
+                result_rows.push(row);
+            }
+        }
+        GetQuery::print_table(&result_fields, &result_rows.iter().collect());
+        log_info!(
+            "{} duplicated keys, {} rows",
+            groups.len(),
+            result_rows.len()
+        );
+        true
+    }
+
+    // returns the indexes of the rows whose key occurs more than once, grouped by key in the order
+    // of the key first occurrence
+    fn group_duplicates(key_idxs: &[usize], rows: &[&Vec<String>]) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group_of_key: HashMap<Vec<&String>, usize> = HashMap::new();
+        for (i, row) in rows.iter().enumerate() {
+            let key: Vec<&String> = key_idxs.iter().map(|&idx| &row[idx]).collect();
+            match group_of_key.get(&key) {
+                Some(&group_idx) => groups[group_idx].push(i),
+                None => {
+                    group_of_key.insert(key, groups.len());
+                    groups.push(vec![i]);
+                }
+            }
+        }
+        groups.retain(|group| group.len() > 1);
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_data() -> Vec<Vec<String>> {
+        vec![
+            vec!["1".to_string(), "bob".to_string()],
+            vec!["2".to_string(), "jack".to_string()],
+            vec!["1".to_string(), "ossama".to_string()],
+            vec!["3".to_string(), "bob".to_string()],
+            vec!["1".to_string(), "bob".to_string()],
+        ]
+    }
+
+    #[test]
+    fn group_duplicates_single_key() {
+        let rows = get_data();
+        let rows_ref: Vec<&Vec<String>> = rows.iter().collect();
+        let groups = DuplicatesQuery::group_duplicates(&[0], &rows_ref);
+        assert_eq!(groups, vec![vec![0, 2, 4]]);
+
+        let groups = DuplicatesQuery::group_duplicates(&[1], &rows_ref);
+        assert_eq!(groups, vec![vec![0, 3, 4]]);
+    }
+
+    #[test]
+    fn group_duplicates_composite_key() {
+        let rows = get_data();
+        let rows_ref: Vec<&Vec<String>> = rows.iter().collect();
+        let groups = DuplicatesQuery::group_duplicates(&[0, 1], &rows_ref);
+        assert_eq!(groups, vec![vec![0, 4]]);
+    }
+
+    #[test]
+    fn group_duplicates_no_duplicates() {
+        let rows = get_data();
+        let rows_ref: Vec<&Vec<String>> = rows[0..2].iter().collect();
+        let groups = DuplicatesQuery::group_duplicates(&[0], &rows_ref);
+        assert!(groups.is_empty());
+    }
+}
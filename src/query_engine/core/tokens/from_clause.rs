@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::ParseResult;
+use super::value;
+use crate::csv_parser::{self, Dialect};
+use crate::log_error;
+use crate::OPTIONS;
+use crate::session::{Session, Table};
+
+#[derive(Debug, PartialEq)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Anti,
+}
+
+// a csv file or a table loaded with the open command
+#[derive(Debug)]
+struct TableSource {
+    path: Option<String>,
+    alias: String,
+}
+
+#[derive(Debug)]
+struct Join {
+    join_type: JoinType,
+    source: TableSource,
+    lhs_field: String,
+    rhs_field: String,
+}
+
+#[derive(Debug)]
+pub struct FromClause {
+    source: TableSource,
+    joins: Vec<Join>,
+    // the indexes of the lexemes of the clause in the query
+    span: Range<usize>,
+}
+
+impl FromClause {
+    pub fn parse(lexemes: &[String], idx: usize) -> (ParseResult<Self>, usize) {
+        match lexemes.get(idx) {
+            Some(lexeme) if lexeme == "from" => {}
+            // there is no from key word
+            _ => return (ParseResult::None, idx - 1),
+        }
+        let (source, mut last_idx) = match Self::parse_source(lexemes, idx + 1) {
+            Some(val) => val,
+            None => return (ParseResult::Err, idx),
+        };
+        let mut joins: Vec<Join> = Vec::new();
+        loop {
+            let (join_type, join_idx) = match lexemes.get(last_idx + 1).map(|l| l.as_str()) {
+                Some("join") | Some("inner") => (JoinType::Inner, last_idx + 1),
+                Some("left") => (JoinType::Left, last_idx + 1),
+                Some("anti") => (JoinType::Anti, last_idx + 1),
+                _ => break,
+            };
+            // the join type is followed by the join key word (ex: left join)
+            let join_idx = if lexemes[join_idx] == "join" {
+                join_idx
+            } else if lexemes.get(join_idx + 1).is_some_and(|l| l == "join") {
+                join_idx + 1
+            } else {
+                log_error!("expecting the join key word after '{}'", lexemes[join_idx]);
+                return (ParseResult::Err, join_idx);
+            };
+            let (source, source_idx) = match Self::parse_source(lexemes, join_idx + 1) {
+                Some(val) => val,
+                None => return (ParseResult::Err, join_idx),
+            };
+            // on $lhs == $rhs
+            let on_lexemes = lexemes.get(source_idx + 1..source_idx + 5);
+            let join_fields = match on_lexemes {
+                Some([on, lhs, op, rhs]) if on == "on" && op == "==" => {
+                    match (value::parse_field_name(lhs), value::parse_field_name(rhs)) {
+                        (Some(lhs), Some(rhs)) => Some((lhs, rhs)),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            let Some((lhs_field, rhs_field)) = join_fields else {
+                log_error!("a join expect a condition: on $table1.field == $table2.field");
+                return (ParseResult::Err, source_idx);
+            };
+            joins.push(Join {
+                join_type,
+                source,
+                lhs_field,
+                rhs_field,
+            });
+            last_idx = source_idx + 4;
+        }
+        let span = idx..last_idx + 1;
+        (ParseResult::Val(FromClause { source, joins, span }), last_idx)
+    }
+
+    // parses a file path and its optional alias ("employees.csv" as emp) or the name of an open
+    // table, when there is no alias the name of the file without the extension is used
+    fn parse_source(lexemes: &[String], idx: usize) -> Option<(TableSource, usize)> {
+        let Some(lexeme) = lexemes.get(idx) else {
+            log_error!("expecting a file path between quotes or a table name");
+            return None;
+        };
+        let Some(path) = value::parse_literal(lexeme) else {
+            return Some((
+                TableSource {
+                    path: None,
+                    alias: lexeme.clone(),
+                },
+                idx,
+            ));
+        };
+        if lexemes.get(idx + 1).is_some_and(|l| l == "as") {
+            match lexemes.get(idx + 2) {
+                Some(alias) if !alias.is_empty() && !alias.contains('.') => {
+                    return Some((
+                        TableSource {
+                            path: Some(path),
+                            alias: alias.clone(),
+                        },
+                        idx + 2,
+                    ));
+                }
+                _ => {
+                    log_error!("expecting a table name without dots after the as key word");
+                    return None;
+                }
+            }
+        }
+        let alias = Table::alias_from_path(&path);
+        Some((
+            TableSource {
+                path: Some(path),
+                alias,
+            },
+            idx,
+        ))
+    }
+
+    // loads the files and joins them, the fields of joined tables are prefixed by the table name
+    // (ex: employees.id)
+    pub fn load(&self, session: &Session) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+        let (mut fields, mut rows) = self.source.load(session)?;
+        if self.joins.is_empty() {
+            return Some((fields, rows));
+        }
+        fields = Self::qualify(&self.source.alias, &fields);
+        for join in self.joins.iter() {
+            let (rhs_fields, rhs_rows) = join.source.load(session)?;
+            let rhs_fields = Self::qualify(&join.source.alias, &rhs_fields);
+            (fields, rows) = join.run(&self.aliases(), fields, rows, rhs_fields, rhs_rows)?;
+        }
+        Some((fields, rows))
+    }
+
+    // replaces the field names of the query that name a joined field without its table (ex: $salary)
+    // by the qualified name (ex: $employees.salary), returns None if a name is in several tables
+    pub fn qualify_lexemes(&self, lexemes: &[String], fields: &[String]) -> Option<Vec<String>> {
+        if self.joins.is_empty() {
+            return Some(lexemes.to_vec());
+        }
+        let aliases = self.aliases();
+        let mut qualified: Vec<String> = Vec::new();
+        for (i, lexeme) in lexemes.iter().enumerate() {
+            // the join conditions were already resolved on the tables they join
+            let name = value::parse_field_name(lexeme).filter(|_| !self.span.contains(&i));
+            match name {
+                Some(name) => match joined_field_idx(&aliases, fields, &name).ok()? {
+                    Some(idx) => qualified.push(value::field_reference(&fields[idx])),
+                    None => qualified.push(lexeme.clone()),
+                },
+                None => qualified.push(lexeme.clone()),
+            }
+        }
+        Some(qualified)
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        let joined = self.joins.iter().map(|join| join.source.alias.as_str());
+        std::iter::once(self.source.alias.as_str()).chain(joined).collect()
+    }
+
+    fn qualify(alias: &str, fields: &[String]) -> Vec<String> {
+        fields.iter().map(|f| format!("{alias}.{f}")).collect()
+    }
+}
+
+// returns the index of a joined field named by its qualified name (ex: employees.id) or by its name
+// alone if a single table has a field with that name, an ambiguous name is reported as an error
+fn joined_field_idx(aliases: &[&str], fields: &[String], name: &str) -> Result<Option<usize>, ()> {
+    if let Some(idx) = value::get_field_idx(fields, name) {
+        return Ok(Some(idx));
+    }
+    let mut matches = aliases
+        .iter()
+        .filter_map(|alias| value::get_field_idx(fields, &format!("{alias}.{name}")));
+    let Some(idx) = matches.next() else {
+        return Ok(None);
+    };
+    if matches.next().is_some() {
+        log_error!(
+            "the field name {} is ambiguous, it should be prefixed by the table name",
+            name
+        );
+        return Err(());
+    }
+    Ok(Some(idx))
+}
+
+impl TableSource {
+    fn load(&self, session: &Session) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+        match &self.path {
+            Some(path) => {
+                let dialect = Dialect::of_file(path, None);
+                csv_parser::parse_file(path, &dialect, None, OPTIONS.get().unwrap())
+                    .map(|file| (file.fields, file.rows))
+            }
+            None => match session.get(&self.alias) {
+                Some(table) => Some((table.fields.clone(), table.rows.clone())),
+                None => {
+                    log_error!("no table named {}", self.alias);
+                    None
+                }
+            },
+        }
+    }
+}
+
+impl Join {
+    // a hash join, the rows of the joined table are indexed by the join key then every row of the
+    // lhs table looks for the rows that have the same key
+    fn run(
+        &self,
+        aliases: &[&str],
+        lhs_fields: Vec<String>,
+        lhs_rows: Vec<Vec<String>>,
+        rhs_fields: Vec<String>,
+        rhs_rows: Vec<Vec<String>>,
+    ) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+        // the condition can name the fields in any order (on $b.id == $a.id)
+        let idx = |fields: &[String], name: &str| joined_field_idx(aliases, fields, name).ok();
+        let (lhs_idx, rhs_idx) = match (
+            idx(&lhs_fields, &self.lhs_field)?,
+            idx(&rhs_fields, &self.rhs_field)?,
+        ) {
+            (Some(lhs_idx), Some(rhs_idx)) => (lhs_idx, rhs_idx),
+            _ => match (
+                idx(&lhs_fields, &self.rhs_field)?,
+                idx(&rhs_fields, &self.lhs_field)?,
+            ) {
+                (Some(lhs_idx), Some(rhs_idx)) => (lhs_idx, rhs_idx),
+                _ => {
+                    log_error!(
+                        "the join condition should compare a field of '{}' to a field of the previous tables",
+                        self.source.alias
+                    );
+                    return None;
+                }
+            },
+        };
+        let mut index: HashMap<&String, Vec<usize>> = HashMap::new();
+        for (i, row) in rhs_rows.iter().enumerate() {
+            index.entry(&row[rhs_idx]).or_default().push(i);
+        }
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for lhs_row in lhs_rows.iter() {
+            let matches = index.get(&lhs_row[lhs_idx]);
+            match self.join_type {
+                JoinType::Inner => {
+                    for &i in matches.into_iter().flatten() {
+                        rows.push([lhs_row.clone(), rhs_rows[i].clone()].concat());
+                    }
+                }
+                JoinType::Left => match matches {
+                    Some(matches) => {
+                        for &i in matches.iter() {
+                            rows.push([lhs_row.clone(), rhs_rows[i].clone()].concat());
+                        }
+                    }
+                    None => {
+                        let empty = vec!["".to_string(); rhs_fields.len()];
+                        rows.push([lhs_row.clone(), empty].concat());
+                    }
+                },
+                JoinType::Anti => {
+                    if matches.is_none() {
+                        rows.push(lhs_row.clone());
+                    }
+                }
+            }
+        }
+        if self.join_type == JoinType::Anti {
+            // an anti join only keeps the rows of the lhs table
+            return Some((lhs_fields, rows));
+        }
+        Some(([lhs_fields, rhs_fields].concat(), rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|v| v.to_string()).collect())
+            .collect()
+    }
+
+    // the fields and rows of the employees table then the fields and rows of the departments table
+    type Data = (Vec<String>, Vec<Vec<String>>, Vec<String>, Vec<Vec<String>>);
+
+    fn get_data() -> Data {
+        let employees_fields = FromClause::qualify("e", &["name".to_string(), "dept".to_string()]);
+        let employees = to_rows(&[&["bob", "1"], &["jack", "2"], &["ossama", "3"]]);
+        let depts_fields = FromClause::qualify("d", &["id".to_string(), "budget".to_string()]);
+        let depts = to_rows(&[&["1", "100"], &["2", "200"], &["2", "250"]]);
+        (employees_fields, employees, depts_fields, depts)
+    }
+
+    fn join(join_type: JoinType) -> Join {
+        Join {
+            join_type,
+            source: TableSource {
+                path: Some("d.csv".to_string()),
+                alias: "d".to_string(),
+            },
+            lhs_field: "e.dept".to_string(),
+            rhs_field: "id".to_string(),
+        }
+    }
+
+    #[test]
+    fn inner_join() {
+        let (lf, lr, rf, rr) = get_data();
+        let (fields, rows) = join(JoinType::Inner).run(&["e", "d"], lf, lr, rf, rr).unwrap();
+        assert_eq!(fields, vec!["e.name", "e.dept", "d.id", "d.budget"]);
+        assert_eq!(
+            rows,
+            to_rows(&[
+                &["bob", "1", "1", "100"],
+                &["jack", "2", "2", "200"],
+                &["jack", "2", "2", "250"]
+            ])
+        );
+    }
+
+    #[test]
+    fn left_join() {
+        let (lf, lr, rf, rr) = get_data();
+        let (_, rows) = join(JoinType::Left).run(&["e", "d"], lf, lr, rf, rr).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3], vec!["ossama", "3", "", ""]);
+    }
+
+    #[test]
+    fn anti_join() {
+        let (lf, lr, rf, rr) = get_data();
+        let (fields, rows) = join(JoinType::Anti).run(&["e", "d"], lf, lr, rf, rr).unwrap();
+        assert_eq!(fields, vec!["e.name", "e.dept"]);
+        assert_eq!(rows, to_rows(&[&["ossama", "3"]]));
+    }
+
+    #[test]
+    fn join_condition_in_any_order() {
+        let (lf, lr, rf, rr) = get_data();
+        let mut reversed = join(JoinType::Inner);
+        reversed.lhs_field = "d.id".to_string();
+        reversed.rhs_field = "dept".to_string();
+        let (_, rows) = reversed.run(&["e", "d"], lf, lr, rf, rr).unwrap();
+        assert_eq!(rows.len(), 3);
+    }
+
+    fn parse(query: &str) -> (Vec<String>, FromClause) {
+        let lexemes: Vec<String> = query.split(' ').map(|l| l.to_string()).collect();
+        let from_idx = lexemes.iter().position(|l| l == "from").unwrap();
+        match FromClause::parse(&lexemes, from_idx).0 {
+            ParseResult::Val(from_clause) => (lexemes, from_clause),
+            _ => panic!("the from clause should parse"),
+        }
+    }
+
+    #[test]
+    fn short_names_of_joined_fields() {
+        let fields: Vec<String> = ["e.name", "e.dept", "e.id", "d.id", "d.budget"]
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        let (lexemes, from_clause) =
+            parse(r#"get $name from "e.csv" as e join "d.csv" as d on $dept == $id where $budget > 100"#);
+        let qualified = from_clause.qualify_lexemes(&lexemes, &fields).unwrap();
+        assert_eq!(qualified[1], "$e.name");
+        // the join condition is left as written
+        assert_eq!(qualified[13], "$id");
+        assert_eq!(qualified[15], "$d.budget");
+
+        let (lexemes, from_clause) =
+            parse(r#"get $id from "e.csv" as e join "d.csv" as d on $dept == $id"#);
+        assert!(from_clause.qualify_lexemes(&lexemes, &fields).is_none());
+    }
+
+    #[test]
+    fn short_names_without_join() {
+        let fields = vec!["price.usd".to_string()];
+        let (lexemes, from_clause) = parse(r#"get $usd from "p.csv""#);
+        let qualified = from_clause.qualify_lexemes(&lexemes, &fields).unwrap();
+        assert_eq!(qualified, lexemes);
+        assert_eq!(value::get_field_idx(&fields, "usd"), None);
+    }
+}
@@ -2,8 +2,10 @@ use super::ParseResult;
 use crate::log_error;
 
 use super::value;
+use crate::sampling::{Rng, SampleSize};
 use core::f32;
 use std::cmp::Ordering;
+use std::collections::HashSet;
 
 #[derive(Debug)]
 pub enum Functions {
@@ -13,10 +15,20 @@ pub enum Functions {
     ReverseNSort(String),
     Head(usize),
     Tail(usize),
+    Distinct,
+    DistinctOn(String),
+    Offset(usize),
+    Limit(usize),
+    Page(usize, usize), // the page number (starting from 1) and the page size
+    Sample(SampleSize, Option<u64>), // the size of the sample and the seed
+    Shuffle(Option<u64>),
 }
 impl Functions {
-    pub fn get_available_functions_names<'a>() -> Vec<&'a str> {
-      return vec!["sort", "rsort", "nsort", "rnsort", "head", "tail"];
+    pub fn get_available_functions_names() -> Vec<&'static str> {
+      return vec![
+          "sort", "rsort", "nsort", "rnsort", "head", "tail", "distinct", "distinct-on", "offset",
+          "limit", "page", "sample", "shuffle",
+      ];
     }
 }
 #[derive(Debug)]
@@ -32,6 +44,52 @@ impl Function {
                 log_error!("no function named {}", lexeme);
                 return (ParseResult::Err, idx);
               }
+                match lexeme.as_str() {
+                    "distinct" => {
+                        // the distinct function does not take a parameter
+                        return (
+                            ParseResult::Val(Function {
+                                function_name: Functions::Distinct,
+                            }),
+                            idx + 1,
+                        );
+                    }
+                    "shuffle" => {
+                        // shuffle (seed S)?
+                        return match Self::parse_seed(lexemes, idx + 1) {
+                            Some((seed, next_idx)) => (
+                                ParseResult::Val(Function {
+                                    function_name: Functions::Shuffle(seed),
+                                }),
+                                next_idx,
+                            ),
+                            None => (ParseResult::Err, idx),
+                        };
+                    }
+                    "sample" => {
+                        // sample (N | N%) (seed S)?
+                        let size = match lexemes.get(idx + 1) {
+                            Some(param) => SampleSize::parse(param),
+                            None => None,
+                        };
+                        let Some(size) = size else {
+                            log_error!(
+                                "the function sample expect a number of rows or a percentage (ex: sample 100 or sample 10%)"
+                            );
+                            return (ParseResult::Err, idx);
+                        };
+                        return match Self::parse_seed(lexemes, idx + 2) {
+                            Some((seed, next_idx)) => (
+                                ParseResult::Val(Function {
+                                    function_name: Functions::Sample(size, seed),
+                                }),
+                                next_idx,
+                            ),
+                            None => (ParseResult::Err, idx),
+                        };
+                    }
+                    _ => {}
+                }
                 idx += 1;
                 match lexemes.get(idx) {
                     Some(param) => match value::parse_field_name(param) {
@@ -70,7 +128,15 @@ impl Function {
                                     idx + 1,
                                 );
                             }
-                            "head" | "tail" => {
+                            "distinct-on" => {
+                                return (
+                                    ParseResult::Val(Function {
+                                        function_name: Functions::DistinctOn(field_name),
+                                    }),
+                                    idx + 1,
+                                );
+                            }
+                            "head" | "tail" | "offset" | "limit" | "page" => {
                               // these functions does not accept a field name as a parameter
                                 log_error!(
                                     "the function {} expect a parameter of type number",
@@ -101,7 +167,42 @@ impl Function {
                                         idx + 1,
                                     );
                                 }
-                                "sort" | "rsort" | "nsort" | "reverse-nsort" => {
+                                "offset" => {
+                                    return (
+                                        ParseResult::Val(Function {
+                                            function_name: Functions::Offset(val.round() as usize),
+                                        }),
+                                        idx + 1,
+                                    );
+                                }
+                                "limit" => {
+                                    return (
+                                        ParseResult::Val(Function {
+                                            function_name: Functions::Limit(val.round() as usize),
+                                        }),
+                                        idx + 1,
+                                    );
+                                }
+                                "page" => {
+                                    // page N size M
+                                    let page = val.round() as usize;
+                                    if page == 0 {
+                                        log_error!("the pages are numbered starting from 1");
+                                        return (ParseResult::Err, idx);
+                                    }
+                                    match Self::parse_page_size(lexemes, idx + 1) {
+                                        Some(size) => {
+                                            return (
+                                                ParseResult::Val(Function {
+                                                    function_name: Functions::Page(page, size),
+                                                }),
+                                                idx + 3,
+                                            );
+                                        }
+                                        None => return (ParseResult::Err, idx),
+                                    }
+                                }
+                                "sort" | "rsort" | "nsort" | "rnsort" | "distinct-on" => {
                                     log_error!(
                                         "the function {} expect a parameter of type field name",
                                         lexeme
@@ -132,10 +233,40 @@ impl Function {
             None => return (ParseResult::None, idx),
         }
     }
-    pub fn run(&self, fields: &Vec<String>, rows: &mut Vec<&Vec<String>>) {
+    // parses the optional 'seed S' part of the sample and shuffle functions, returns the seed and
+    // the index of the next lexeme
+    fn parse_seed(lexemes: &[String], idx: usize) -> Option<(Option<u64>, usize)> {
+        if lexemes.get(idx).is_none_or(|lexeme| lexeme != "seed") {
+            return Some((None, idx));
+        }
+        match lexemes.get(idx + 1).map(|seed| seed.parse::<u64>()) {
+            Some(Ok(seed)) => Some((Some(seed), idx + 2)),
+            _ => {
+                log_error!("the seed should be a positive integer (ex: seed 42)");
+                None
+            }
+        }
+    }
+    // parses the 'size M' part of the page function
+    fn parse_page_size(lexemes: &[String], idx: usize) -> Option<usize> {
+        if lexemes.get(idx).is_none_or(|lexeme| lexeme != "size") {
+            log_error!("the page function expect a size: page N size M");
+            return None;
+        }
+        match lexemes.get(idx + 1).and_then(value::parse_number) {
+            Some(size) if size.round() >= 1f32 => Some(size.round() as usize),
+            _ => {
+                log_error!("the size of a page should be a number greater than 0");
+                None
+            }
+        }
+    }
+    // selected_idxs are the indexes of the fields picked by the get selector, the distinct function
+    // compares the rows only on these fields
+    pub fn run(&self, fields: &Vec<String>, selected_idxs: &[usize], rows: &mut Vec<&Vec<String>>) {
         match &self.function_name {
             Functions::Sort(field_name) => {
-                match fields.iter().position(|name| name == field_name) {
+                match value::get_field_idx(fields, field_name) {
                     Some(idx) => {
                         self.sort(idx, rows);
                     }
@@ -145,7 +276,7 @@ impl Function {
                 }
             }
             Functions::ReverseSort(field_name) => {
-                match fields.iter().position(|name| name == field_name) {
+                match value::get_field_idx(fields, field_name) {
                     Some(idx) => {
                         self.reverse_sort(idx, rows);
                     }
@@ -155,7 +286,7 @@ impl Function {
                 }
             }
             Functions::NSort(field_name) => {
-                match fields.iter().position(|name| name == field_name) {
+                match value::get_field_idx(fields, field_name) {
                     Some(idx) => {
                         self.n_sort(idx, rows);
                     }
@@ -166,7 +297,7 @@ impl Function {
             }
 
             Functions::ReverseNSort(field_name) => {
-                match fields.iter().position(|name| name == field_name) {
+                match value::get_field_idx(fields, field_name) {
                     Some(idx) => {
                         self.n_reverse_sort(idx, rows);
                     }
@@ -176,15 +307,59 @@ impl Function {
                 }
             }
 
-            Functions::Head(arg) => {
-                rows.drain(arg..);
+            Functions::Head(arg) | Functions::Limit(arg) => {
+                rows.truncate(*arg);
             }
             Functions::Tail(arg) => {
-                rows.drain(0..rows.len() - arg);
+                rows.drain(0..rows.len().saturating_sub(*arg));
+            }
+            Functions::Offset(arg) => {
+                rows.drain(0..(*arg).min(rows.len()));
+            }
+            Functions::Page(page, size) => {
+                let start = ((page - 1).saturating_mul(*size)).min(rows.len());
+                rows.drain(0..start);
+                rows.truncate(*size);
+            }
+            Functions::Sample(size, seed) => {
+                let mut rng = Rng::new(*seed);
+                let count = size.count_of(rows.len());
+                // a partial shuffle of the rows indexes picks the sampled rows, they are then
+                // sorted so the sample keeps the order of the file
+                let mut idxs: Vec<usize> = (0..rows.len()).collect();
+                for i in 0..count {
+                    let j = i + rng.below(idxs.len() - i);
+                    idxs.swap(i, j);
+                }
+                idxs.truncate(count);
+                idxs.sort_unstable();
+                *rows = idxs.into_iter().map(|i| rows[i]).collect();
+            }
+            Functions::Shuffle(seed) => {
+                Rng::new(*seed).shuffle(rows);
+            }
+            Functions::Distinct => {
+                self.distinct(selected_idxs, rows);
+            }
+            Functions::DistinctOn(field_name) => {
+                match value::get_field_idx(fields, field_name) {
+                    Some(idx) => {
+                        self.distinct(&[idx], rows);
+                    }
+                    None => {
+                        log_error!("no field named {}", field_name);
+                    }
+                }
             }
         }
     }
 
+    // keeps only the first row of every group of rows that have the same values at field_idxs
+    fn distinct(&self, field_idxs: &[usize], rows: &mut Vec<&Vec<String>>) {
+        let mut seen: HashSet<Vec<String>> = HashSet::new();
+        rows.retain(|row| seen.insert(field_idxs.iter().map(|&i| row[i].clone()).collect()));
+    }
+
     fn sort(&self, field_idx: usize, rows: &mut Vec<&Vec<String>>) {
         rows.sort_by(|a, b| a[field_idx].cmp(&b[field_idx]));
     }
@@ -263,7 +438,7 @@ mod tests {
         let function = Function {
             function_name: Functions::Sort("name".to_string()),
         };
-        function.run(&fields, &mut rows_ref);
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
         let expected_rows = vec![
             vec!["bob", "45", "60"],
             vec!["jack", "20", "90"],
@@ -280,7 +455,7 @@ mod tests {
         let function = Function {
             function_name: Functions::Sort("name".to_string()),
         };
-        function.run(&fields, &mut rows_ref);
+        function.run(&fields, &[], &mut rows_ref);
         let expected_rows = vec![];
         assert!(equal_rows(&expected_rows, &rows_ref))
     }
@@ -293,7 +468,7 @@ mod tests {
         let function = Function {
             function_name: Functions::ReverseSort("name".to_string()),
         };
-        function.run(&fields, &mut rows_ref);
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
         let expected_rows = vec![
             vec!["ossama", "27", "100"],
             vec!["jack", "20", "90"],
@@ -310,7 +485,7 @@ mod tests {
         let function = Function {
             function_name: Functions::ReverseNSort("points".to_string()),
         };
-        function.run(&fields, &mut rows_ref);
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
         let expected_rows = vec![
             vec!["ossama", "27", "100"],
             vec!["jack", "20", "90"],
@@ -327,7 +502,7 @@ mod tests {
         let function = Function {
             function_name: Functions::NSort("points".to_string()),
         };
-        function.run(&fields, &mut rows_ref);
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
         let expected_rows = vec![
             vec!["bob", "45", "60"],
             vec!["jack", "20", "90"],
@@ -344,11 +519,113 @@ mod tests {
         let function = Function {
             function_name: Functions::Sort("age".to_string()),
         };
-        function.run(&fields, &mut rows_ref);
+        function.run(&fields, &[], &mut rows_ref);
         let expected_rows = vec![];
         assert!(equal_rows(&expected_rows, &rows_ref))
     }
 
+    #[test]
+    fn head_tail_bounds_test() {
+        use super::*;
+        let (fields, rows) = get_data();
+        let mut rows_ref = prepair_rows(&rows);
+        let function = Function {
+            function_name: Functions::Head(10),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        assert_eq!(rows_ref.len(), 3);
+
+        let function = Function {
+            function_name: Functions::Tail(10),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        assert_eq!(rows_ref.len(), 3);
+
+        let function = Function {
+            function_name: Functions::Tail(1),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        assert!(equal_rows(&vec![vec!["jack", "20", "90"]], &rows_ref));
+    }
+    #[test]
+    fn offset_limit_test() {
+        use super::*;
+        let (fields, rows) = get_data();
+        let mut rows_ref = prepair_rows(&rows);
+        let function = Function {
+            function_name: Functions::Offset(1),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        let function = Function {
+            function_name: Functions::Limit(1),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        assert!(equal_rows(&vec![vec!["ossama", "27", "100"]], &rows_ref));
+
+        let mut rows_ref = prepair_rows(&rows);
+        let function = Function {
+            function_name: Functions::Offset(5),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        assert!(rows_ref.is_empty());
+    }
+    #[test]
+    fn page_test() {
+        use super::*;
+        let (fields, rows) = get_data();
+        let mut rows_ref = prepair_rows(&rows);
+        let function = Function {
+            function_name: Functions::Page(2, 2),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        assert!(equal_rows(&vec![vec!["jack", "20", "90"]], &rows_ref));
+
+        let mut rows_ref = prepair_rows(&rows);
+        let function = Function {
+            function_name: Functions::Page(3, 2),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        assert!(rows_ref.is_empty());
+    }
+
+    #[test]
+    fn sample_test() {
+        use super::*;
+        let (fields, rows) = get_data();
+        let sample = |size: SampleSize| {
+            let mut rows_ref = prepair_rows(&rows);
+            let function = Function {
+                function_name: Functions::Sample(size, Some(3)),
+            };
+            function.run(&fields, &[0, 1, 2], &mut rows_ref);
+            rows_ref
+        };
+        let first = sample(SampleSize::Count(2));
+        assert_eq!(first.len(), 2);
+        // the same seed gives the same sample
+        assert_eq!(first, sample(SampleSize::Count(2)));
+        assert_eq!(sample(SampleSize::Count(10)).len(), 3);
+        assert_eq!(sample(SampleSize::Percent(50f32)).len(), 2);
+        assert!(sample(SampleSize::Percent(0f32)).is_empty());
+    }
+    #[test]
+    fn shuffle_test() {
+        use super::*;
+        let (fields, rows) = get_data();
+        let mut rows_ref = prepair_rows(&rows);
+        let function = Function {
+            function_name: Functions::Shuffle(Some(11)),
+        };
+        function.run(&fields, &[0, 1, 2], &mut rows_ref);
+        let mut again = prepair_rows(&rows);
+        function.run(&fields, &[0, 1, 2], &mut again);
+        assert_eq!(rows_ref, again);
+        rows_ref.sort();
+        let mut original = prepair_rows(&rows);
+        original.sort();
+        assert_eq!(rows_ref, original);
+    }
+
     fn equal_rows(expected_rows: &Vec<Vec<&str>>, rows: &Vec<&Vec<String>>) -> bool {
         if expected_rows.len() != rows.len() {
             return false;
@@ -29,12 +29,17 @@ impl FunctionCall {
             ParseResult::Err => ParseResult::Err,
         }
     }
-    pub fn evaluate(&self, fields: &Vec<String>, valid_rows: &mut Vec<&Vec<String>>) -> () {
+    pub fn evaluate(
+        &self,
+        fields: &Vec<String>,
+        selected_idxs: &[usize],
+        valid_rows: &mut Vec<&Vec<String>>,
+    ) -> () {
         if let Some(function) = &self.function {
-            function.run(&fields, valid_rows);
+            function.run(&fields, selected_idxs, valid_rows);
         }
         if let Some(function_call) = &self.function_call {
-            function_call.evaluate(&fields, valid_rows);
+            function_call.evaluate(&fields, selected_idxs, valid_rows);
         }
     }
 }
@@ -1,16 +1,22 @@
 use super::ParseResult;
+use super::from_clause::FromClause;
 use super::function_call::FunctionCall;
 use super::value;
 use super::where_clause::WhereClause;
 use crate::OPTIONS;
+use crate::csv_parser;
+use crate::session::Session;
 use crate::log_error;
 use terminal_size::{Width, terminal_size};
 
 #[derive(Debug)]
 pub struct GetQuery {
     selector: Vec<String>,
+    from_clause: Option<FromClause>,
     where_clause: Option<WhereClause>,
     function_call: Option<FunctionCall>,
+    // the query is parsed again once the field names of a join are known
+    lexemes: Vec<String>,
 }
 
 impl GetQuery {
@@ -27,12 +33,14 @@ impl GetQuery {
                 if *lexeme == "*" {
                     // the case where the user select all fields (ex: get * ....)
                     selector.push("*".to_string());
-                    match Self::parse_where_clause_and_function_call(lexemes, 2) {
-                        ParseResult::Val((where_clause, function_call)) => {
+                    match Self::parse_clauses(lexemes, 2) {
+                        ParseResult::Val((from_clause, where_clause, function_call)) => {
                             return ParseResult::Val(GetQuery {
                                 selector,
+                                from_clause,
                                 where_clause,
                                 function_call,
+                                lexemes: lexemes.to_vec(),
                             });
                         }
                         ParseResult::None => return ParseResult::None,
@@ -43,12 +51,14 @@ impl GetQuery {
                 if let Some((mut fields_names, idx)) = Self::parse_field_name_list(lexemes, 1) {
                     selector.append(&mut fields_names); // adding the fields names to the selector
 
-                    match Self::parse_where_clause_and_function_call(lexemes, idx + 1) {
-                        ParseResult::Val((where_clause, function_call)) => {
+                    match Self::parse_clauses(lexemes, idx + 1) {
+                        ParseResult::Val((from_clause, where_clause, function_call)) => {
                             return ParseResult::Val(GetQuery {
                                 selector,
+                                from_clause,
                                 where_clause,
                                 function_call,
+                                lexemes: lexemes.to_vec(),
                             });
                         }
                         ParseResult::None => return ParseResult::None,
@@ -65,13 +75,27 @@ impl GetQuery {
             }
         }
     }
-    pub fn parse_where_clause_and_function_call(
+    // parses the clauses that follow the selector: FromClause? WhereClause? FunctionCall?
+    pub fn parse_clauses(
         lexemes: &[String],
         idx: usize,
-    ) -> ParseResult<(Option<WhereClause>, Option<FunctionCall>)> {
+    ) -> ParseResult<(Option<FromClause>, Option<WhereClause>, Option<FunctionCall>)> {
+        let final_from_clause: Option<FromClause>;
         let final_where_clause: Option<WhereClause>;
         let final_function_call: Option<FunctionCall>;
-        let (where_clause_parse_result, last_idx) = WhereClause::parse(lexemes, idx);
+        let (from_clause_parse_result, last_idx) = FromClause::parse(lexemes, idx);
+        match from_clause_parse_result {
+            ParseResult::Val(from_clause) => {
+                final_from_clause = Some(from_clause);
+            }
+            ParseResult::None => {
+                final_from_clause = None;
+            }
+            ParseResult::Err => {
+                return ParseResult::Err;
+            }
+        }
+        let (where_clause_parse_result, last_idx) = WhereClause::parse(lexemes, last_idx + 1);
 
         match where_clause_parse_result {
             ParseResult::Val(where_clause) => {
@@ -96,10 +120,10 @@ impl GetQuery {
                 return ParseResult::Err;
             }
         }
-        return ParseResult::Val((final_where_clause, final_function_call));
+        return ParseResult::Val((final_from_clause, final_where_clause, final_function_call));
     }
     // a helper method that parse the list of fields names
-    fn parse_field_name_list(
+    pub fn parse_field_name_list(
         lexemes: &[String],
         mut start_idx: usize,
     ) -> Option<(Vec<String>, usize)> {
@@ -122,7 +146,56 @@ impl GetQuery {
         }
         return Some((fields_names, start_idx - 1));
     }
-    pub fn evaluate(&self, fields: &Vec<String>, rows: &mut Vec<Vec<String>>) {
+    pub fn evaluate(&self, session: &Session) -> bool {
+        self.select(session, |idxs, fields, rows| {
+            self.print_result(idxs, fields, rows)
+        })
+    }
+    // returns the selected fields and the values of these fields in the result rows
+    pub fn run(&self, session: &Session) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+        let mut result: Option<(Vec<String>, Vec<Vec<String>>)> = None;
+        self.select(session, |idxs, fields, rows| {
+            let result_fields = idxs.iter().map(|&i| fields[i].clone()).collect();
+            let result_rows = rows
+                .iter()
+                .map(|row| idxs.iter().map(|&i| row[i].clone()).collect())
+                .collect();
+            result = Some((result_fields, result_rows));
+        });
+        result
+    }
+    // finds the rows of the query result then calls on_result with the indexes of the selected
+    // fields, the fields and the result rows, returns false if the query failed
+    fn select<F>(&self, session: &Session, on_result: F) -> bool
+    where
+        F: FnOnce(&Vec<usize>, &Vec<String>, &Vec<&Vec<String>>),
+    {
+        if let Some(from_clause) = &self.from_clause {
+            // the query runs on the tables of the from clause instead of the current table
+            let Some((from_fields, from_rows)) = from_clause.load(session) else {
+                return false;
+            };
+            // the names of the joined fields are resolved once, before the rows are evaluated
+            let Some(lexemes) = from_clause.qualify_lexemes(&self.lexemes, &from_fields) else {
+                return false;
+            };
+            return match Self::parse(&lexemes) {
+                ParseResult::Val(query) => query.select_on(&from_fields, &from_rows, on_result),
+                _ => false,
+            };
+        }
+        let table = session.current();
+        self.select_on(&table.fields, &table.rows, on_result)
+    }
+    fn select_on<F>(&self, fields: &Vec<String>, rows: &[Vec<String>], on_result: F) -> bool
+    where
+        F: FnOnce(&Vec<usize>, &Vec<String>, &Vec<&Vec<String>>),
+    {
+        let idxs: Vec<usize>;
+        match self.get_selected_idxs(fields) {
+            Some(selected_idxs) => idxs = selected_idxs,
+            None => return false,
+        }
         // will hold the rows that satisfies the condition
         let mut valid_rows: Vec<&Vec<String>> = Vec::new();
 
@@ -142,27 +215,44 @@ impl GetQuery {
         }
         if let Some(function_call) = &self.function_call {
             // evaluate the function call on the valid rows
-            function_call.evaluate(fields, &mut valid_rows);
+            function_call.evaluate(fields, &idxs, &mut valid_rows);
         }
-        self.print_result(fields, &valid_rows);
+        on_result(&idxs, fields, &valid_rows);
+        true
     }
-
-    fn print_result(&self, fields: &Vec<String>, rows: &Vec<&Vec<String>>) {
-        println!();
+    // prints all the fields of the given rows using the same layout as the get command
+    pub fn print_table(fields: &Vec<String>, rows: &Vec<&Vec<String>>) {
+        let get_query = GetQuery {
+            selector: vec!["*".to_string()],
+            from_clause: None,
+            where_clause: None,
+            function_call: None,
+            lexemes: Vec::new(),
+        };
+        let idxs: Vec<usize> = (0..fields.len()).collect();
+        get_query.print_result(&idxs, fields, rows);
+    }
+    // returns the indexes of the fields picked by the selector
+    fn get_selected_idxs(&self, fields: &[String]) -> Option<Vec<usize>> {
         let mut idxs: Vec<usize> = Vec::new();
         for selector in self.selector.iter() {
             if selector == "*" {
                 idxs = (0..fields.len()).collect();
                 break;
             }
-            match fields.iter().position(|f| f == selector) {
+            match value::get_field_idx(fields, selector) {
                 Some(idx) => idxs.push(idx),
                 None => {
                     log_error!("no field named {}", selector);
-                    return;
+                    return None;
                 }
             }
         }
+        Some(idxs)
+    }
+
+    fn print_result(&self, idxs: &Vec<usize>, fields: &Vec<String>, rows: &Vec<&Vec<String>>) {
+        println!();
         let mut longest_vals_len = self.get_longest_vals_in_rows(idxs.clone(), fields, rows);
         let terminal_width: u16; // the width of the terminal (in char)
 
@@ -182,7 +272,7 @@ impl GetQuery {
                 }
             }
         }
-        self.print_rows(&idxs, fields, rows, &longest_vals_len);
+        self.print_rows(idxs, fields, rows, &longest_vals_len);
     }
     // this method returns the length longest value in each column
     fn get_longest_vals_in_rows(
@@ -218,11 +308,13 @@ impl GetQuery {
         longerst_vals_len: &Vec<usize>,
     ) {
         let options = OPTIONS.get().unwrap();
-        let separator: &String;
-        match options.get(&crate::Options::FieldsSeparator) {
-            Some(sep) => separator = sep,
-            None => unreachable!("there is no default value for the separator option"),
-        }
+        let default_separator = csv_parser::DEFAULT_SEPARATOR.to_string();
+        // the rows are printed with the output separator or the -s separator, the separator of the
+        // file is guessed
+        let separator = options
+            .get(&crate::Options::OutputSeparator)
+            .or(options.get(&crate::Options::FieldsSeparator))
+            .unwrap_or(&default_separator);
         self.print_row(idxs, &fields, longerst_vals_len, separator);
         for row in rows {
             self.print_row(idxs, &row, longerst_vals_len, separator);
@@ -55,7 +55,7 @@ impl InsertColumnQuery {
         }
     }
 
-    pub fn evaluate(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> () {
+    pub fn evaluate(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> bool {
         let mut where_clause_eval_results: Vec<bool>;
         if let Some(where_clause) = &self.where_clause {
             where_clause_eval_results = vec![false; rows.len()];
@@ -64,11 +64,11 @@ impl InsertColumnQuery {
                 where_clause_eval_results[i] = where_clause.evaluate(fields, &rows[i]);
             }
             self.assign_list
-                .insert_column_evaluation(fields, rows, &where_clause_eval_results);
+                .insert_column_evaluation(fields, rows, &where_clause_eval_results)
         } else {
             where_clause_eval_results = vec![true; rows.len()];
             self.assign_list
-                .insert_column_evaluation(fields, rows, &where_clause_eval_results);
+                .insert_column_evaluation(fields, rows, &where_clause_eval_results)
         }
     }
 }
@@ -35,7 +35,7 @@ impl InsertRowQuery {
         }
     }
 
-    pub fn evaluate(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> () {
-        self.assign_list.insert_row_evaluation(fields, rows);
+    pub fn evaluate(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> bool {
+        self.assign_list.insert_row_evaluation(fields, rows)
     }
 }
@@ -18,6 +18,11 @@ pub mod insert_column_query;
 pub mod insert_row_query;
 pub mod delete_query;
 pub mod aggregation_function_call;
+pub mod duplicates_query;
+pub mod diff_query;
+pub mod column_query;
+pub mod from_clause;
+pub mod set_operation_query;
 
 pub enum ParseResult<T> {
     Val(T),
@@ -24,23 +24,37 @@ enum Modifier {
     StringModifier(StringModifier),
 }
 
+// the modifiers looked up by the parser, the help and the tab completion
+const MODIFIERS: [(&str, Modifier); 9] = [
+    ("+", Modifier::ArithmeticModifier(ArithmeticModifier::Plus)),
+    ("-", Modifier::ArithmeticModifier(ArithmeticModifier::Minus)),
+    ("*", Modifier::ArithmeticModifier(ArithmeticModifier::Multiply)),
+    ("/", Modifier::ArithmeticModifier(ArithmeticModifier::Divide)),
+    ("%", Modifier::ArithmeticModifier(ArithmeticModifier::Modulo)),
+    ("^", Modifier::ArithmeticModifier(ArithmeticModifier::Power)),
+    ("||", Modifier::StringModifier(StringModifier::Concatenate)),
+    ("to-upper", Modifier::StringModifier(StringModifier::ToUpperCase)),
+    ("to-lower", Modifier::StringModifier(StringModifier::ToLowerCase)),
+];
+
+pub fn get_available_modifiers_names() -> Vec<&'static str> {
+    MODIFIERS.iter().map(|(name, _)| *name).collect()
+}
+
 impl Modifier {
-    pub fn get_modifier_from_lexeme(lexeme: &String) -> Self {
-        match lexeme.as_str() {
-            // Arithmetic modifiers
-            "+" => Modifier::ArithmeticModifier(ArithmeticModifier::Plus),
-            "-" => Modifier::ArithmeticModifier(ArithmeticModifier::Minus),
-            "*" => Modifier::ArithmeticModifier(ArithmeticModifier::Multiply),
-            "/" => Modifier::ArithmeticModifier(ArithmeticModifier::Divide),
-            "%" => Modifier::ArithmeticModifier(ArithmeticModifier::Modulo),
-            "^" => Modifier::ArithmeticModifier(ArithmeticModifier::Power),
+    pub fn get_modifier_from_lexeme(lexeme: &str) -> Option<Self> {
+        MODIFIERS
+            .into_iter()
+            .find(|(name, _)| *name == lexeme)
+            .map(|(_, modifier)| modifier)
+    }
 
-            // String modifiers
-            "||" => Modifier::StringModifier(StringModifier::Concatenate),
-            "to-upper" => Modifier::StringModifier(StringModifier::ToUpperCase),
-            "to-lower" => Modifier::StringModifier(StringModifier::ToLowerCase),
-            _ => unreachable!("invalid modifier {lexeme}"),
-        }
+    // the modifiers that take a right hand side value
+    fn is_binary(&self) -> bool {
+        !matches!(
+            self,
+            Modifier::StringModifier(StringModifier::ToUpperCase | StringModifier::ToLowerCase)
+        )
     }
 }
 #[derive(Debug)]
@@ -76,10 +90,10 @@ impl Modification {
                 }
                 match lexemes.get(idx + 1) {
                     Some(lexeme2) => {
-                        match lexeme2.as_str() {
+                        match Modifier::get_modifier_from_lexeme(lexeme2) {
                             // lexeme2 must be a valid modifier
-                            "+" | "-" | "*" | "/" | "%" | "^" | "||" => {
-                                modifier = Some(Modifier::get_modifier_from_lexeme(lexeme2));
+                            Some(binary_modifier) if binary_modifier.is_binary() => {
+                                modifier = Some(binary_modifier);
                                 match lexemes.get(idx + 2) {
                                     Some(_) => {
                                         let (modification_parse_result, last_idx) =
@@ -115,21 +129,19 @@ impl Modification {
                                     }
                                 }
                             }
-                            "to-lower" | "to-upper" => {
-                                modifier = Some(Modifier::get_modifier_from_lexeme(lexeme2));
+                            Some(unary_modifier) => {
+                                modifier = Some(unary_modifier);
                                 let mut additional_modifier: Option<Modifier> = None;
                                 let mut rhs: Option<Box<Modification>> = None;
                                 let mut last_idx = idx + 1;
                                 match lexemes.get(idx + 2) {
-                                    Some(lexeme) => match lexeme.as_str() {
-                                        "+" | "-" | "*" | "/" | "%" | "^" | "||" => {
+                                    Some(lexeme) => match Modifier::get_modifier_from_lexeme(lexeme) {
+                                        Some(binary_modifier) if binary_modifier.is_binary() => {
                                             let (modification_parse_result, last_idx1) =
                                                 Modification::parse(lexemes, idx + 3);
                                             match modification_parse_result {
                                                 ParseResult::Val(modification) => {
-                                                    additional_modifier = Some(
-                                                        Modifier::get_modifier_from_lexeme(lexeme),
-                                                    );
+                                                    additional_modifier = Some(binary_modifier);
                                                     rhs = Some(Box::new(modification));
                                                     last_idx = last_idx1;
                                                 }
@@ -198,7 +210,7 @@ impl Modification {
                         // extracting the value of the lhs
                         Value::Number(number) => lhs = *number,
                         Value::FieldName(field_name) => {
-                            match fields.iter().position(|f| f == field_name) {
+                            match value::get_field_idx(fields, field_name) {
                                 Some(idx) => match row[idx].parse::<f32>() {
                                     Ok(val) => lhs = val,
                                     Err(_) => {
@@ -262,7 +274,7 @@ impl Modification {
                         // extracting the lhs
                         Value::Literal(val) => lhs = val.clone(),
                         Value::FieldName(field_name) => {
-                            match fields.iter().position(|f| f == field_name) {
+                            match value::get_field_idx(fields, field_name) {
                                 Some(idx) => lhs = row[idx].clone(),
                                 None => {
                                     log_error!("no field named {}", field_name);
@@ -368,7 +380,7 @@ impl Modification {
             },
             None => match &self.lhs {
                 // the case where the modification is just a value
-                Value::FieldName(val) => match fields.iter().position(|f| f == val) {
+                Value::FieldName(val) => match value::get_field_idx(fields, val) {
                     Some(idx) => return Some(row[idx].clone()),
                     None => {
                         log_error!("no field named {}", val);
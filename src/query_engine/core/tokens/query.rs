@@ -5,6 +5,18 @@ use super::insert_column_query::InsertColumnQuery;
 use super::insert_row_query::InsertRowQuery;
 use super::set_query::SetQuery;
 use super::aggregation_function_call::AggregationFunctionCall;
+use super::duplicates_query::DuplicatesQuery;
+use super::diff_query::DiffQuery;
+use super::column_query::ColumnQuery;
+use crate::history::{CellChange, Change, ColumnsChange};
+use crate::csv_parser;
+use crate::schema;
+use crate::log_error;
+use crate::log_info;
+use crate::{OPTIONS, Options};
+use std::collections::HashSet;
+use super::set_operation_query::SetOperationQuery;
+use crate::session::Session;
 
 #[derive(Debug)]
 pub struct Query {
@@ -14,10 +26,33 @@ pub struct Query {
     insert_row_query: Option<InsertRowQuery>,
     delete_query: Option<DeleteQuery>,
     aggregation_function_call: Option<AggregationFunctionCall>,
+    duplicates_query: Option<DuplicatesQuery>,
+    set_operation_query: Option<SetOperationQuery>,
+    diff_query: Option<DiffQuery>,
+    column_query: Option<ColumnQuery>,
 }
 
 impl Query {
     pub fn parse(lexemes: &[String]) -> Option<Self> {
+        // the set operations are made of get queries so they are parsed first
+        match SetOperationQuery::parse(lexemes) {
+            ParseResult::Val(set_operation_query) => {
+                return Some(Query {
+                    get_query: None,
+                    set_query: None,
+                    insert_column_query: None,
+                    insert_row_query: None,
+                    delete_query: None,
+                    aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: Some(set_operation_query),
+                    diff_query: None,
+                    column_query: None,
+                });
+            }
+            ParseResult::None => {}
+            ParseResult::Err => return None,
+        }
         match GetQuery::parse(lexemes) {
             ParseResult::Val(get_query) => {
                 return Some(Query {
@@ -27,6 +62,10 @@ impl Query {
                     insert_row_query: None,
                     delete_query: None,
                     aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: None,
                 });
             }
             ParseResult::None => {}
@@ -41,6 +80,10 @@ impl Query {
                     insert_row_query: None,
                     delete_query: None,
                     aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: None,
                 });
             }
             ParseResult::None => {}
@@ -55,6 +98,10 @@ impl Query {
                     insert_row_query: None,
                     delete_query: None,
                     aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: None,
                 });
             }
             ParseResult::None => {}
@@ -69,6 +116,10 @@ impl Query {
                     insert_row_query: Some(insert_row_query),
                     delete_query: None,
                     aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: None,
                 });
             }
             ParseResult::None => {}
@@ -83,6 +134,10 @@ impl Query {
                     insert_row_query: None,
                     delete_query: Some(delete_query),
                     aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: None,
                 });
             }
             ParseResult::None => {}
@@ -97,6 +152,64 @@ impl Query {
                     insert_row_query: None,
                     delete_query: None,
                     aggregation_function_call: Some(aggregation_function_call),
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: None,
+                });
+            }
+            ParseResult::None => {}
+            ParseResult::Err => return None,
+        }
+        match DuplicatesQuery::parse(lexemes) {
+            ParseResult::Val(duplicates_query) => {
+                return Some(Query {
+                    get_query: None,
+                    set_query: None,
+                    insert_column_query: None,
+                    insert_row_query: None,
+                    delete_query: None,
+                    aggregation_function_call: None,
+                    duplicates_query: Some(duplicates_query),
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: None,
+                });
+            }
+            ParseResult::None => {}
+            ParseResult::Err => return None,
+        }
+        match DiffQuery::parse(lexemes) {
+            ParseResult::Val(diff_query) => {
+                return Some(Query {
+                    get_query: None,
+                    set_query: None,
+                    insert_column_query: None,
+                    insert_row_query: None,
+                    delete_query: None,
+                    aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: Some(diff_query),
+                    column_query: None,
+                });
+            }
+            ParseResult::None => {}
+            ParseResult::Err => return None,
+        }
+        match ColumnQuery::parse(lexemes) {
+            ParseResult::Val(column_query) => {
+                return Some(Query {
+                    get_query: None,
+                    set_query: None,
+                    insert_column_query: None,
+                    insert_row_query: None,
+                    delete_query: None,
+                    aggregation_function_call: None,
+                    duplicates_query: None,
+                    set_operation_query: None,
+                    diff_query: None,
+                    column_query: Some(column_query),
                 });
             }
             ParseResult::None => {}
@@ -104,30 +217,234 @@ impl Query {
         }
         None
     }
-    pub fn evaluate(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
+    // returns false if the query failed, the changes made by the command are added to the history of
+    // the table
+    pub fn evaluate(&self, session: &mut Session, command: &str) -> bool {
         match &self.get_query {
-            Some(get_query) => return get_query.evaluate(fields, rows),
+            Some(get_query) => return get_query.evaluate(session),
             None => {}
         }
-        match &self.set_query {
-            Some(set_query) => return set_query.evaluate(fields, rows),
+        match &self.set_operation_query {
+            Some(set_operation_query) => return set_operation_query.evaluate(session),
             None => {}
         }
-        match &self.insert_column_query {
-            Some(insert_column_query) => return insert_column_query.evaluate(fields, rows),
+        match &self.diff_query {
+            Some(diff_query) => return diff_query.evaluate(),
             None => {}
         }
-        match &self.insert_row_query {
-            Some(insert_row_query) => return insert_row_query.evaluate(fields, rows),
-            None => {}
+        let table = session.current_mut();
+        let (fields, rows) = (&mut table.fields, &mut table.rows);
+        if !self.confirm(fields, rows) {
+            log_info!("the changes are cancelled");
+            return false;
         }
-        match &self.delete_query {
-            Some(delete_query) => return delete_query.evaluate(fields, rows),
-            None => {}
+        let Some((succeeded, change)) = self.apply(fields, rows) else {
+            return self.evaluate_read_only(fields, rows);
+        };
+        // a query that fails is reverted
+        if !succeeded {
+            change.undo(fields, rows);
+            log_info!("the query failed, its changes are reverted");
+            return false;
+        }
+        if let Some(schema) = &table.schema {
+            let violations = schema.check_change(fields, rows, &change);
+            if !violations.is_empty() {
+                schema::report(&violations);
+                change.undo(fields, rows);
+                log_error!("the query breaks the schema, its changes are reverted");
+                return false;
+            }
+        }
+        log_info!("{}", change.summary());
+        table.history.record(command, change);
+        true
+    }
+    // runs a mutating query and returns if it succeeded with the change it made, None if the query
+    // does not modify the table
+    fn apply(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> Option<(bool, Change)> {
+        if let Some(set_query) = &self.set_query {
+            let mut cells: Vec<CellChange> = Vec::new();
+            let succeeded = set_query.evaluate(fields, rows, &mut cells);
+            Some((succeeded, Change::Cells(cells)))
+        } else if let Some(insert_column_query) = &self.insert_column_query {
+            let old_fields = fields.clone();
+            let succeeded = insert_column_query.evaluate(fields, rows);
+            let change = ColumnsChange::appended(old_fields, fields, rows);
+            Some((succeeded, Change::Columns(change)))
+        } else if let Some(insert_row_query) = &self.insert_row_query {
+            let old_len = rows.len();
+            let succeeded = insert_row_query.evaluate(fields, rows);
+            Some((succeeded, Change::AddedRows(rows[old_len..].to_vec())))
+        } else if let Some(delete_query) = &self.delete_query {
+            let mut removed: Vec<(usize, Vec<String>)> = Vec::new();
+            let succeeded = delete_query.evaluate(fields, rows, &mut removed);
+            Some((succeeded, Change::RemovedRows(removed)))
+        } else if let Some(column_query) = &self.column_query {
+            match column_query.evaluate(fields, rows) {
+                Some(change) => Some((true, Change::Columns(change))),
+                None => Some((false, Change::Cells(Vec::new()))),
+            }
+        } else {
+            None
+        }
+    }
+    // the number of rows a set or a delete query touches, a row of a set counts even if its values
+    // stay the same
+    fn touched_rows(change: &Change) -> usize {
+        match change {
+            Change::Cells(cells) => cells.iter().map(|cell| cell.row).collect::<HashSet<usize>>().len(),
+            Change::RemovedRows(removed) => removed.len(),
+            Change::AddedRows(_) | Change::Columns(_) => 0,
+        }
+    }
+    // with the --confirm-over option the user is asked before a set or a delete touches more rows
+    // than the limit, the rows are counted like explain-changes does before anything is applied
+    fn confirm(&self, fields: &Vec<String>, rows: &[Vec<String>]) -> bool {
+        let limit = OPTIONS
+            .get()
+            .unwrap()
+            .get(&Options::ConfirmOver)
+            .and_then(|limit| limit.parse::<usize>().ok());
+        let Some(limit) = limit else {
+            return true;
+        };
+        // an other query or a failing one, the failure is reported when the query is applied
+        let Some(change) = self.preview(fields, rows) else {
+            return true;
+        };
+        let touched = Self::touched_rows(&change);
+        if touched <= limit {
+            return true;
+        }
+        loop {
+            let question = format!("{touched} rows will be changed, continue? (y or n): ");
+            match csv_parser::ask(&question).as_deref() {
+                Some("y") => return true,
+                Some("n") | None => return false,
+                Some(_) => continue,
+            }
+        }
+    }
+    // the change a set or a delete query would make, computed from the where clause without
+    // modifying the rows, None if the query is an other one or an assignment fails
+    fn preview(&self, fields: &Vec<String>, rows: &[Vec<String>]) -> Option<Change> {
+        if let Some(set_query) = &self.set_query {
+            set_query.preview(fields, rows).map(Change::Cells)
+        } else {
+            self.delete_query
+                .as_ref()
+                .map(|delete_query| Change::RemovedRows(delete_query.preview(fields, rows)))
         }
+    }
+    // shows the rows a set or a delete query would change without applying it, the rows that
+    // satisfy the where clause are listed even if their values stay the same
+    pub fn explain(&self, session: &Session) -> bool {
+        if self.set_query.is_none() && self.delete_query.is_none() {
+            log_error!("only set and delete queries can be explained");
+            return false;
+        }
+        let table = session.current();
+        let fields = &table.fields;
+        let Some(change) = self.preview(fields, &table.rows) else {
+            return false;
+        };
+        match &change {
+            Change::Cells(cells) if !cells.is_empty() => {
+                // the rows are numbered from 1 like the lines of the file after the header
+                let preview_fields = ["row", "field", "before", "after"].map(|f| f.to_string());
+                let preview_rows: Vec<Vec<String>> = cells
+                    .iter()
+                    .map(|cell| {
+                        let row = (cell.row + 1).to_string();
+                        let field = fields[cell.field].clone();
+                        vec![row, field, cell.old.clone(), cell.new.clone()]
+                    })
+                    .collect();
+                GetQuery::print_table(&preview_fields.to_vec(), &preview_rows.iter().collect());
+            }
+            Change::RemovedRows(removed) if !removed.is_empty() => {
+                let mut preview_fields = vec!["row".to_string()];
+                preview_fields.extend(fields.iter().cloned());
+                let preview_rows: Vec<Vec<String>> = removed
+                    .iter()
+                    .map(|(idx, row)| [vec![(idx + 1).to_string()], row.clone()].concat())
+                    .collect();
+                GetQuery::print_table(&preview_fields, &preview_rows.iter().collect());
+            }
+            _ => {}
+        }
+        let action = if self.delete_query.is_some() { "deleted" } else { "modified" };
+        log_info!(
+            "{} rows would be {}, nothing was applied",
+            Self::touched_rows(&change),
+            action
+        );
+        true
+    }
+    fn evaluate_read_only(&self, fields: &Vec<String>, rows: &Vec<Vec<String>>) -> bool {
         match &self.aggregation_function_call {
-            Some(aggregation_function_call) => return aggregation_function_call.evaluate(fields, rows),
+            Some(aggregation_function_call) => {
+                aggregation_function_call.evaluate(fields, rows);
+                return true;
+            }
             None => {},
         }
+        match &self.duplicates_query {
+            Some(duplicates_query) => return duplicates_query.evaluate(fields, rows),
+            None => {}
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(row: usize, field: usize) -> CellChange {
+        CellChange {
+            row,
+            field,
+            old: "1".to_string(),
+            new: "2".to_string(),
+        }
+    }
+
+    #[test]
+    fn touched_rows() {
+        // two cells of the same row count as one row
+        let change = Change::Cells(vec![cell(0, 0), cell(0, 1), cell(3, 0)]);
+        assert_eq!(Query::touched_rows(&change), 2);
+        let change = Change::RemovedRows(vec![(1, vec![]), (4, vec![])]);
+        assert_eq!(Query::touched_rows(&change), 2);
+        let change = Change::AddedRows(vec![vec![]]);
+        assert_eq!(Query::touched_rows(&change), 0);
+    }
+
+    #[test]
+    fn preview_without_applying() {
+        let lexemes = |query: &str| -> Vec<String> { query.split(' ').map(String::from).collect() };
+        let fields = vec!["id".to_string(), "age".to_string()];
+        let rows: Vec<Vec<String>> = [["1", "20"], ["2", "40"], ["3", "50"]]
+            .iter()
+            .map(|row| row.map(String::from).to_vec())
+            .collect();
+
+        // the second row keeps its value but it is still counted
+        let query = Query::parse(&lexemes("set $age = 40 where $age >= 30")).unwrap();
+        let Some(Change::Cells(cells)) = query.preview(&fields, &rows) else {
+            panic!("expecting the changed cells");
+        };
+        assert_eq!(cells.iter().map(|cell| cell.row).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!((cells[0].old.as_str(), cells[0].new.as_str()), ("40", "40"));
+        assert_eq!(Query::touched_rows(&Change::Cells(cells)), 2);
+
+        let query = Query::parse(&lexemes("delete where $id == 1")).unwrap();
+        let Some(Change::RemovedRows(removed)) = query.preview(&fields, &rows) else {
+            panic!("expecting the removed rows");
+        };
+        assert_eq!(removed, vec![(0, rows[0].clone())]);
+        assert_eq!(rows.len(), 3);
     }
 }
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use super::ParseResult;
+use super::get_query::GetQuery;
+use crate::log_error;
+use crate::session::Session;
+
+#[derive(Debug, PartialEq)]
+pub enum SetOperator {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+// combines the results of get queries (ex: get * from old except get * from new), the columns of
+// the results are matched by name
+#[derive(Debug)]
+pub struct SetOperationQuery {
+    get_query: GetQuery,
+    operations: Vec<(SetOperator, GetQuery)>,
+}
+
+impl SetOperationQuery {
+    pub fn parse(lexemes: &[String]) -> ParseResult<Self> {
+        if lexemes.first().is_none_or(|lexeme| lexeme != "get") {
+            return ParseResult::None;
+        }
+        // the positions of the set operators split the lexemes into get queries
+        let mut operators: Vec<(usize, SetOperator, usize)> = Vec::new(); // start, operator, end
+        let mut i = 0;
+        while i < lexemes.len() {
+            match lexemes[i].as_str() {
+                "union" if lexemes.get(i + 1).is_some_and(|l| l == "all") => {
+                    operators.push((i, SetOperator::UnionAll, i + 2));
+                    i += 1;
+                }
+                "union" => operators.push((i, SetOperator::Union, i + 1)),
+                "intersect" => operators.push((i, SetOperator::Intersect, i + 1)),
+                "except" => operators.push((i, SetOperator::Except, i + 1)),
+                _ => {}
+            }
+            i += 1;
+        }
+        if operators.is_empty() {
+            return ParseResult::None;
+        }
+
+        let get_query = match Self::parse_get_query(&lexemes[..operators[0].0]) {
+            Some(get_query) => get_query,
+            None => return ParseResult::Err,
+        };
+        let mut operations: Vec<(SetOperator, GetQuery)> = Vec::new();
+        let ends: Vec<usize> = operators
+            .iter()
+            .skip(1)
+            .map(|(start, _, _)| *start)
+            .chain(std::iter::once(lexemes.len()))
+            .collect();
+        for ((_, operator, start), end) in operators.into_iter().zip(ends) {
+            match Self::parse_get_query(&lexemes[start..end]) {
+                Some(get_query) => operations.push((operator, get_query)),
+                None => return ParseResult::Err,
+            }
+        }
+        ParseResult::Val(SetOperationQuery {
+            get_query,
+            operations,
+        })
+    }
+
+    fn parse_get_query(lexemes: &[String]) -> Option<GetQuery> {
+        match GetQuery::parse(lexemes) {
+            ParseResult::Val(get_query) => Some(get_query),
+            ParseResult::None => {
+                log_error!("union, intersect and except can only combine get queries");
+                None
+            }
+            ParseResult::Err => None,
+        }
+    }
+
+    pub fn evaluate(&self, session: &Session) -> bool {
+        let Some((fields, mut rows)) = self.get_query.run(session) else {
+            return false;
+        };
+        for (operator, get_query) in self.operations.iter() {
+            let Some((rhs_fields, rhs_rows)) = get_query.run(session) else {
+                return false;
+            };
+            let Some(rhs_rows) = Self::match_fields(&fields, &rhs_fields, rhs_rows) else {
+                return false;
+            };
+            rows = Self::combine(operator, rows, rhs_rows);
+        }
+        GetQuery::print_table(&fields, &rows.iter().collect());
+        true
+    }
+
+    // reorders the values of the rhs rows so they follow the order of the lhs fields
+    fn match_fields(
+        fields: &[String],
+        rhs_fields: &[String],
+        rhs_rows: Vec<Vec<String>>,
+    ) -> Option<Vec<Vec<String>>> {
+        let mut idxs: Vec<usize> = Vec::new();
+        for field in fields.iter() {
+            match rhs_fields.iter().position(|f| f == field) {
+                Some(idx) if fields.len() == rhs_fields.len() => idxs.push(idx),
+                _ => {
+                    log_error!(
+                        "the queries should select the same fields: {} and {}",
+                        fields.join(", "),
+                        rhs_fields.join(", ")
+                    );
+                    return None;
+                }
+            }
+        }
+        Some(
+            rhs_rows
+                .into_iter()
+                .map(|row| idxs.iter().map(|&i| row[i].clone()).collect())
+                .collect(),
+        )
+    }
+
+    fn combine(
+        operator: &SetOperator,
+        lhs: Vec<Vec<String>>,
+        rhs: Vec<Vec<String>>,
+    ) -> Vec<Vec<String>> {
+        match operator {
+            SetOperator::UnionAll => [lhs, rhs].concat(),
+            SetOperator::Union => Self::distinct([lhs, rhs].concat()),
+            SetOperator::Intersect => {
+                let rhs: HashSet<Vec<String>> = rhs.into_iter().collect();
+                Self::distinct(lhs.into_iter().filter(|row| rhs.contains(row)).collect())
+            }
+            SetOperator::Except => {
+                let rhs: HashSet<Vec<String>> = rhs.into_iter().collect();
+                Self::distinct(lhs.into_iter().filter(|row| !rhs.contains(row)).collect())
+            }
+        }
+    }
+
+    // removes the repeated rows, the first occurrence of every row is kept in its place
+    fn distinct(rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+        let mut seen: HashSet<Vec<String>> = HashSet::new();
+        rows.into_iter()
+            .filter(|row| seen.insert(row.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|v| v.to_string()).collect())
+            .collect()
+    }
+
+    fn get_data() -> (Vec<Vec<String>>, Vec<Vec<String>>) {
+        let yesterday = to_rows(&[&["1", "bob"], &["2", "jack"], &["2", "jack"], &["3", "ann"]]);
+        let today = to_rows(&[&["1", "bob"], &["3", "ann"], &["4", "ossama"]]);
+        (yesterday, today)
+    }
+
+    #[test]
+    fn union() {
+        let (yesterday, today) = get_data();
+        let rows = SetOperationQuery::combine(&SetOperator::Union, yesterday, today);
+        assert_eq!(
+            rows,
+            to_rows(&[
+                &["1", "bob"],
+                &["2", "jack"],
+                &["3", "ann"],
+                &["4", "ossama"]
+            ])
+        );
+    }
+
+    #[test]
+    fn union_all() {
+        let (yesterday, today) = get_data();
+        let rows = SetOperationQuery::combine(&SetOperator::UnionAll, yesterday, today);
+        assert_eq!(rows.len(), 7);
+    }
+
+    #[test]
+    fn intersect() {
+        let (yesterday, today) = get_data();
+        let rows = SetOperationQuery::combine(&SetOperator::Intersect, yesterday, today);
+        assert_eq!(rows, to_rows(&[&["1", "bob"], &["3", "ann"]]));
+    }
+
+    #[test]
+    fn except() {
+        let (yesterday, today) = get_data();
+        let rows = SetOperationQuery::combine(&SetOperator::Except, yesterday, today);
+        assert_eq!(rows, to_rows(&[&["2", "jack"]]));
+    }
+
+    #[test]
+    fn match_fields_by_name() {
+        let fields = vec!["id".to_string(), "name".to_string()];
+        let rhs_fields = vec!["name".to_string(), "id".to_string()];
+        let rows = SetOperationQuery::match_fields(&fields, &rhs_fields, to_rows(&[&["bob", "1"]]));
+        assert_eq!(rows, Some(to_rows(&[&["1", "bob"]])));
+
+        let rhs_fields = vec!["name".to_string(), "age".to_string()];
+        let rows = SetOperationQuery::match_fields(&fields, &rhs_fields, to_rows(&[&["bob", "1"]]));
+        assert_eq!(rows, None);
+    }
+}
@@ -1,6 +1,7 @@
 use super::ParseResult;
 use super::assign_list::AssignList;
 use super::where_clause::WhereClause;
+use crate::history::CellChange;
 use crate::log_error;
 
 #[derive(Debug)]
@@ -55,19 +56,63 @@ impl SetQuery {
             }
         }
     }
-    pub fn evaluate(&self, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) -> () {
-        if let Some(where_clause) = &self.where_clause {
-            // this for loop will evaluate the where condition for every line
-            for i in 0..rows.len() {
-                if where_clause.evaluate(fields, &rows[i]) {
-                    self.assign_list.set_evaluation(fields, &mut rows[i]);
+    // returns false when an assignment fails, the rows that come after it are not modified, the
+    // modified cells are added to changes
+    pub fn evaluate(
+        &self,
+        fields: &mut Vec<String>,
+        rows: &mut Vec<Vec<String>>,
+        changes: &mut Vec<CellChange>,
+    ) -> bool {
+        for i in 0..rows.len() {
+            if let Some(where_clause) = &self.where_clause {
+                // the where condition is evaluated for every line
+                if !where_clause.evaluate(fields, &rows[i]) {
+                    continue;
                 }
             }
-        } else {
-            for i in 0..rows.len() {
-                self.assign_list.set_evaluation(fields, &mut rows[i]);
+            let old_row = rows[i].clone();
+            let succeeded = self.assign_list.set_evaluation(fields, &mut rows[i]);
+            for (j, old) in old_row.into_iter().enumerate() {
+                if old != rows[i][j] {
+                    changes.push(CellChange {
+                        row: i,
+                        field: j,
+                        old,
+                        new: rows[i][j].clone(),
+                    });
+                }
+            }
+            if !succeeded {
+                return false;
+            }
+        }
+        true
+    }
+    // the assigned cells of the rows that satisfy the where clause, the values that stay the same
+    // are kept, the rows are not modified, None when an assignment fails
+    pub fn preview(&self, fields: &Vec<String>, rows: &[Vec<String>]) -> Option<Vec<CellChange>> {
+        let assigned = self.assign_list.field_indices(fields);
+        let mut changes: Vec<CellChange> = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            if let Some(where_clause) = &self.where_clause
+                && !where_clause.evaluate(fields, row)
+            {
+                continue;
+            }
+            let mut new_row = row.clone();
+            if !self.assign_list.set_evaluation(fields, &mut new_row) {
+                return None;
+            }
+            for &j in assigned.iter() {
+                changes.push(CellChange {
+                    row: i,
+                    field: j,
+                    old: row[j].clone(),
+                    new: new_row[j].clone(),
+                });
             }
         }
-        ()
+        Some(changes)
     }
 }
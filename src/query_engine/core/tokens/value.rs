@@ -44,13 +44,31 @@ pub fn parse_literal(lexeme: &String) -> Option<String> {
     None
 }
 
+// a field is referenced by $name, or by ${name} when its name holds spaces (ex: ${Total Sales})
 pub fn parse_field_name(lexeme: &String) -> Option<String> {
+    if let Some(name) = lexeme.strip_prefix("${").and_then(|l| l.strip_suffix('}')) {
+        return Some(name.to_string());
+    }
     if lexeme.starts_with("$") {
         return Some(lexeme[1..].to_string());
     }
     None
 }
 
+// how a field is referenced in a query
+pub fn field_reference(name: &str) -> String {
+    if name.contains(char::is_whitespace) {
+        format!("${{{name}}}")
+    } else {
+        format!("${name}")
+    }
+}
+
+// returns the index of the field named field_name
+pub fn get_field_idx(fields: &[String], field_name: &str) -> Option<usize> {
+    fields.iter().position(|f| f == field_name)
+}
+
 pub fn parse_number(lexeme: &String) -> Option<f32> {
     match lexeme.parse::<f32>() {
         Ok(val) => return Some(val),
@@ -1,9 +1,13 @@
+use crate::session::Session;
+
 mod core;
 
-pub fn query(query: String, fields: &mut Vec<String>, rows: &mut Vec<Vec<String>>) {
+pub use core::{field_reference, keywords, meta_command};
+
+pub fn query(query: String, session: &mut Session) -> bool {
 
     // splitting the query to a vector of lexemes
-    let lexemes: Vec<String> = query.split(' ').map(|s| s.to_string()).collect();
+    let lexemes: Vec<String> = core::lexemes(&query);
     
-    core::query(&lexemes[..], fields, rows);
+    core::query(&lexemes[..], session)
 }
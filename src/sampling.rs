@@ -0,0 +1,160 @@
+// random sampling helpers shared by the sample/shuffle functions and the --sample loading option
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    Count(usize),
+    Percent(f32),
+}
+
+impl SampleSize {
+    // parses a sample size written as a number of rows (ex: 100) or a percentage (ex: 10%)
+    pub fn parse(lexeme: &str) -> Option<SampleSize> {
+        match lexeme.strip_suffix('%') {
+            Some(percent) => match percent.parse::<f32>() {
+                Ok(val) if (0f32..=100f32).contains(&val) => Some(SampleSize::Percent(val)),
+                _ => None,
+            },
+            None => match lexeme.parse::<usize>() {
+                Ok(val) => Some(SampleSize::Count(val)),
+                Err(_) => None,
+            },
+        }
+    }
+    // the number of rows to keep out of total rows
+    pub fn count_of(&self, total: usize) -> usize {
+        match self {
+            SampleSize::Count(count) => (*count).min(total),
+            SampleSize::Percent(percent) => {
+                ((total as f64 * *percent as f64 / 100f64).round() as usize).min(total)
+            }
+        }
+    }
+}
+
+// a small splitmix64 generator, the same seed always produces the same sequence on every platform
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: Option<u64>) -> Self {
+        match seed {
+            Some(seed) => Rng { state: seed },
+            None => {
+                // no seed was given so the time is used to get a different sample on every call
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0);
+                Rng { state: nanos }
+            }
+        }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    // a random number in 0..bound
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+    // a random number in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+// keeps a uniform random sample of at most `capacity` items from a stream of unknown length,
+// only the sampled items are held in memory (algorithm R)
+pub struct Reservoir<T> {
+    capacity: usize,
+    seen: usize,
+    items: Vec<(usize, T)>,
+    rng: Rng,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize, seed: Option<u64>) -> Self {
+        Reservoir {
+            capacity,
+            seen: 0,
+            items: Vec::new(),
+            rng: Rng::new(seed),
+        }
+    }
+    pub fn push(&mut self, item: T) {
+        if self.items.len() < self.capacity {
+            self.items.push((self.seen, item));
+        } else {
+            let j = self.rng.below(self.seen + 1);
+            if j < self.capacity {
+                self.items[j] = (self.seen, item);
+            }
+        }
+        self.seen += 1;
+    }
+    // the sampled items in the order they were pushed
+    pub fn into_vec(mut self) -> Vec<T> {
+        self.items.sort_by_key(|(position, _)| *position);
+        self.items.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_size_parse() {
+        assert_eq!(SampleSize::parse("10"), Some(SampleSize::Count(10)));
+        assert_eq!(SampleSize::parse("2.5%"), Some(SampleSize::Percent(2.5)));
+        assert_eq!(SampleSize::parse("150%"), None);
+        assert_eq!(SampleSize::parse("abc"), None);
+        assert_eq!(SampleSize::Percent(1f32).count_of(1000), 10);
+        assert_eq!(SampleSize::Count(10).count_of(3), 3);
+    }
+
+    #[test]
+    fn same_seed_same_sample() {
+        let mut first = Reservoir::new(5, Some(42));
+        let mut second = Reservoir::new(5, Some(42));
+        for i in 0..1000 {
+            first.push(i);
+            second.push(i);
+        }
+        let first = first.into_vec();
+        assert_eq!(first.len(), 5);
+        assert_eq!(first, second.into_vec());
+        // the sample keeps the stream order
+        assert!(first.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn reservoir_smaller_than_capacity() {
+        let mut reservoir = Reservoir::new(10, Some(1));
+        for i in 0..3 {
+            reservoir.push(i);
+        }
+        assert_eq!(reservoir.into_vec(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn shuffle_is_a_permutation() {
+        let mut items: Vec<usize> = (0..50).collect();
+        Rng::new(Some(7)).shuffle(&mut items);
+        let mut again: Vec<usize> = (0..50).collect();
+        Rng::new(Some(7)).shuffle(&mut again);
+        assert_eq!(items, again);
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<usize>>());
+    }
+}
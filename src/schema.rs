@@ -0,0 +1,460 @@
+// the constraints of the columns of a csv file, read from a toml or a json schema file:
+//
+// [columns.age]
+// type = "integer"
+// not-null = true
+// min = 0
+// max = 150
+use crate::history::Change;
+use crate::log_error;
+use crate::log_warning;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Integer,
+    Number,
+    Boolean,
+    Text,
+}
+
+impl Kind {
+    pub fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "integer" => Some(Kind::Integer),
+            "number" => Some(Kind::Number),
+            "boolean" => Some(Kind::Boolean),
+            "text" => Some(Kind::Text),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Integer => "integer",
+            Kind::Number => "number",
+            Kind::Boolean => "boolean",
+            Kind::Text => "text",
+        }
+    }
+
+    // if the value is a valid value of the type, every value is a valid text
+    pub fn parses(&self, value: &str) -> bool {
+        match self {
+            Kind::Integer => value.parse::<i64>().is_ok(),
+            Kind::Number => value.parse::<f64>().is_ok(),
+            Kind::Boolean => value == "true" || value == "false",
+            Kind::Text => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    field: String,
+    kind: Option<Kind>,
+    not_null: bool,
+    unique: bool,
+    allowed: Option<Vec<String>>,
+    // the pattern as written in the schema with the regex that matches the whole value
+    pattern: Option<(String, Regex)>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    rules: Vec<Rule>,
+}
+
+// a value or a column that breaks the schema, place is the line of the file or the row of the table
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub place: String,
+    pub message: String,
+}
+
+impl Rule {
+    fn parse(field: &str, constraints: &Value) -> Result<Self, String> {
+        let Value::Object(constraints) = constraints else {
+            return Err(format!(
+                "the constraints of the column {field} should be a table"
+            ));
+        };
+        let mut rule = Rule {
+            field: field.to_string(),
+            kind: None,
+            not_null: false,
+            unique: false,
+            allowed: None,
+            pattern: None,
+            min: None,
+            max: None,
+        };
+        let invalid = |name: &str| format!("invalid {name} for the column {field}");
+        for (name, value) in constraints.iter() {
+            match (name.as_str(), value) {
+                ("type", Value::String(kind)) => {
+                    rule.kind = Some(Kind::from_name(kind).ok_or_else(|| {
+                        format!("unknown type {kind}, use integer, number, boolean or text")
+                    })?);
+                }
+                ("not-null", Value::Bool(not_null)) => rule.not_null = *not_null,
+                ("unique", Value::Bool(unique)) => rule.unique = *unique,
+                ("allowed", Value::Array(values)) => {
+                    let values: Option<Vec<String>> = values
+                        .iter()
+                        .map(|v| match v {
+                            Value::String(s) => Some(s.clone()),
+                            Value::Number(n) => Some(n.to_string()),
+                            Value::Bool(b) => Some(b.to_string()),
+                            _ => None,
+                        })
+                        .collect();
+                    rule.allowed = Some(values.ok_or_else(|| invalid(name))?);
+                }
+                ("pattern", Value::String(pattern)) => {
+                    // the pattern has to match the whole value
+                    let regex = Regex::new(&format!("^(?:{pattern})$"))
+                        .map_err(|_| format!("invalid pattern {pattern} for the column {field}"))?;
+                    rule.pattern = Some((pattern.clone(), regex));
+                }
+                ("min", Value::Number(n)) => rule.min = n.as_f64(),
+                ("max", Value::Number(n)) => rule.max = n.as_f64(),
+                ("type" | "not-null" | "unique" | "allowed" | "pattern" | "min" | "max", _) => {
+                    return Err(invalid(name));
+                }
+                _ => return Err(format!("unknown constraint {name} for the column {field}")),
+            }
+        }
+        Ok(rule)
+    }
+
+    // the problem of a value, the unique constraint is checked on the whole column
+    fn check(&self, value: &str) -> Option<String> {
+        let field = &self.field;
+        if value.is_empty() {
+            return self.not_null.then(|| format!("${field} is empty"));
+        }
+        if let Some(kind) = self.kind
+            && !kind.parses(value)
+        {
+            return Some(format!(
+                "${field}: '{value}' is not of the type {}",
+                kind.name()
+            ));
+        }
+        if let Some(allowed) = &self.allowed
+            && !allowed.iter().any(|v| v == value)
+        {
+            return Some(format!(
+                "${field}: '{value}' is not one of {}",
+                allowed.join(", ")
+            ));
+        }
+        if let Some((pattern, regex)) = &self.pattern
+            && !regex.is_match(value)
+        {
+            return Some(format!("${field}: '{value}' does not match {pattern}"));
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let Ok(number) = value.parse::<f64>() else {
+                return Some(format!("${field}: '{value}' is not a number"));
+            };
+            if let Some(min) = self.min
+                && number < min
+            {
+                return Some(format!("${field}: {value} is less than the min {min}"));
+            }
+            if let Some(max) = self.max
+                && number > max
+            {
+                return Some(format!("${field}: {value} is more than the max {max}"));
+            }
+        }
+        None
+    }
+}
+
+impl Schema {
+    pub fn load(path: &str) -> Option<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => {
+                log_error!("can not open the schema file {path}");
+                return None;
+            }
+        };
+        let value: Result<Value, String> = if path.ends_with(".toml") {
+            toml::from_str::<toml::Table>(&content)
+                .map_err(|e| e.message().to_string())
+                .and_then(|table| serde_json::to_value(table).map_err(|e| e.to_string()))
+        } else if path.ends_with(".json") {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            Err("the schema should be a .toml or a .json file".to_string())
+        };
+        match value.and_then(|value| Self::parse(&value)) {
+            Ok(schema) => Some(schema),
+            Err(e) => {
+                log_error!("invalid schema {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn parse(value: &Value) -> Result<Self, String> {
+        let Some(Value::Object(columns)) = value.get("columns") else {
+            return Err("the schema should have a columns table".to_string());
+        };
+        let rules = columns
+            .iter()
+            .map(|(field, constraints)| Rule::parse(field, constraints))
+            .collect::<Result<Vec<Rule>, String>>()?;
+        Ok(Schema { rules })
+    }
+
+    // checks the rows of a file while they are read
+    pub fn validator(&self, fields: &[String]) -> Validator<'_> {
+        let mut violations = Vec::new();
+        let mut rules = Vec::new();
+        for rule in self.rules.iter() {
+            match fields.iter().position(|f| *f == rule.field) {
+                Some(idx) => rules.push((idx, rule)),
+                None => violations.push(Violation {
+                    place: "header".to_string(),
+                    message: format!("the column ${} of the schema is missing", rule.field),
+                }),
+            }
+        }
+        // the violations of a row are reported in the order of the columns
+        rules.sort_by_key(|(idx, _)| *idx);
+        Validator {
+            rules,
+            seen: HashMap::new(),
+            violations,
+        }
+    }
+
+    // the problems of the values written by a change, the values that were already in the table
+    // are not checked again
+    pub fn check_change(
+        &self,
+        fields: &[String],
+        rows: &[Vec<String>],
+        change: &Change,
+    ) -> Vec<Violation> {
+        let cells: Vec<(usize, usize)> = match change {
+            Change::Cells(cells) => cells.iter().map(|cell| (cell.row, cell.field)).collect(),
+            Change::AddedRows(added) => (rows.len() - added.len()..rows.len())
+                .flat_map(|row| (0..fields.len()).map(move |field| (row, field)))
+                .collect(),
+            Change::RemovedRows(_) => Vec::new(),
+            // the columns that were added or renamed are checked as a whole
+            Change::Columns(columns) => {
+                let missing: Vec<Violation> = self
+                    .rules
+                    .iter()
+                    .filter(|rule| {
+                        columns.old_fields().contains(&rule.field) && !fields.contains(&rule.field)
+                    })
+                    .map(|rule| Violation {
+                        place: "header".to_string(),
+                        message: format!("the column ${} of the schema is removed", rule.field),
+                    })
+                    .collect();
+                if !missing.is_empty() {
+                    return missing;
+                }
+                (0..fields.len())
+                    .filter(|&i| !columns.old_fields().contains(&fields[i]))
+                    .flat_map(|field| (0..rows.len()).map(move |row| (row, field)))
+                    .collect()
+            }
+        };
+        let mut violations = Vec::new();
+        // the occurrences of the values of the unique columns, counted once per column
+        let mut counts: HashMap<usize, HashMap<&str, usize>> = HashMap::new();
+        for (row, field) in cells {
+            let Some(rule) = self.rules.iter().find(|rule| rule.field == fields[field]) else {
+                continue;
+            };
+            let value = &rows[row][field];
+            let mut message = rule.check(value);
+            if message.is_none() && rule.unique && !value.is_empty() {
+                let counts = counts.entry(field).or_insert_with(|| {
+                    let mut counts: HashMap<&str, usize> = HashMap::new();
+                    for row in rows {
+                        *counts.entry(row[field].as_str()).or_default() += 1;
+                    }
+                    counts
+                });
+                if counts.get(value.as_str()).is_some_and(|&count| count > 1) {
+                    message = Some(format!("${}: '{}' is already used", rule.field, value));
+                }
+            }
+            if let Some(message) = message {
+                violations.push(Violation {
+                    place: format!("row {}", row + 1),
+                    message,
+                });
+            }
+        }
+        violations
+    }
+}
+
+// the number of violations shown, a file that does not follow its schema at all would flood the
+// output
+const MAX_REPORTED: usize = 20;
+
+pub fn report(violations: &[Violation]) {
+    for violation in violations.iter().take(MAX_REPORTED) {
+        log_warning!("{}: {}", violation.place, violation.message);
+    }
+    if violations.len() > MAX_REPORTED {
+        log_warning!("and {} more violations", violations.len() - MAX_REPORTED);
+    }
+}
+
+pub struct Validator<'a> {
+    // the index of the column of every rule that matches a field
+    rules: Vec<(usize, &'a Rule)>,
+    // the values of the unique columns with the line where they were seen first
+    seen: HashMap<usize, HashMap<String, usize>>,
+    pub violations: Vec<Violation>,
+}
+
+impl Validator<'_> {
+    pub fn check_row(&mut self, line: usize, row: &[String]) {
+        for (idx, rule) in self.rules.iter() {
+            let value = &row[*idx];
+            let mut message = rule.check(value);
+            if message.is_none() && rule.unique && !value.is_empty() {
+                let seen = self.seen.entry(*idx).or_default();
+                if let Some(first) = seen.get(value) {
+                    message = Some(format!(
+                        "${}: '{}' is already at the line {}",
+                        rule.field, value, first
+                    ));
+                } else {
+                    seen.insert(value.clone(), line);
+                }
+            }
+            if let Some(message) = message {
+                self.violations.push(Violation {
+                    place: format!("line {line}"),
+                    message,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::CellChange;
+    use serde_json::json;
+
+    fn get_schema() -> Schema {
+        Schema::parse(&json!({"columns": {
+            "id": {"type": "integer", "not-null": true, "unique": true},
+            "status": {"allowed": ["active", "inactive"]},
+            "email": {"pattern": "[^@]+@[^@]+"},
+            "age": {"type": "integer", "min": 0, "max": 150},
+        }}))
+        .unwrap()
+    }
+
+    fn fields() -> Vec<String> {
+        ["id", "status", "email", "age"]
+            .map(|f| f.to_string())
+            .to_vec()
+    }
+
+    fn row(values: [&str; 4]) -> Vec<String> {
+        values.map(|v| v.to_string()).to_vec()
+    }
+
+    #[test]
+    fn validate_rows() {
+        let schema = get_schema();
+        let mut validator = schema.validator(&fields());
+        validator.check_row(2, &row(["1", "active", "a@b.c", "30"]));
+        validator.check_row(3, &row(["1", "gone", "ab.c", "200"]));
+        validator.check_row(4, &row(["", "", "", "x"]));
+        let messages: Vec<String> = validator
+            .violations
+            .iter()
+            .map(|v| format!("{}: {}", v.place, v.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 3: $id: '1' is already at the line 2",
+                "line 3: $status: 'gone' is not one of active, inactive",
+                "line 3: $email: 'ab.c' does not match [^@]+@[^@]+",
+                "line 3: $age: 200 is more than the max 150",
+                "line 4: $id is empty",
+                "line 4: $age: 'x' is not of the type integer",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_column() {
+        let schema = get_schema();
+        let fields = ["id", "status", "email"].map(|f| f.to_string()).to_vec();
+        let validator = schema.validator(&fields);
+        assert_eq!(
+            validator.violations,
+            vec![Violation {
+                place: "header".to_string(),
+                message: "the column $age of the schema is missing".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn check_changed_cells() {
+        let schema = get_schema();
+        let rows = vec![
+            row(["1", "active", "a@b.c", "30"]),
+            row(["1", "active", "a@b.c", "-1"]),
+        ];
+        let change = Change::Cells(vec![
+            CellChange {
+                row: 1,
+                field: 0,
+                old: "2".to_string(),
+                new: "1".to_string(),
+            },
+            CellChange {
+                row: 1,
+                field: 3,
+                old: "20".to_string(),
+                new: "-1".to_string(),
+            },
+        ]);
+        let violations = schema.check_change(&fields(), &rows, &change);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].message, "$id: '1' is already used");
+        assert_eq!(violations[1].place, "row 2");
+
+        let change = Change::AddedRows(vec![rows[1].clone()]);
+        assert_eq!(schema.check_change(&fields(), &rows, &change).len(), 2);
+    }
+
+    #[test]
+    fn invalid_schemas() {
+        let schema = |value: Value| Schema::parse(&value).err();
+        assert!(schema(json!({"columns": {"id": {"type": "date"}}})).is_some());
+        assert!(schema(json!({"columns": {"id": {"unique": "yes"}}})).is_some());
+        assert!(schema(json!({"columns": {"id": {"size": 3}}})).is_some());
+        assert!(schema(json!({"fields": {}})).is_some());
+    }
+}
@@ -0,0 +1,728 @@
+// the tables loaded in the REPL, every table keeps its own file path, dialect and unsaved changes
+use crate::compression::Compression;
+use crate::csv_parser::{self, BadRow, BadRowAction, Dialect};
+use crate::history::History;
+use crate::log_error;
+use crate::log_info;
+use crate::log_warning;
+use crate::schema::Schema;
+use crate::{OPTIONS, Options};
+use std::path::Path;
+
+#[derive(Clone)]
+pub struct Table {
+    pub alias: String,
+    pub path: String,
+    pub dialect: Dialect,
+    pub fields: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    // the version of the history that was loaded or saved last
+    saved_version: u64,
+    // when only a sample of the file is loaded, saving would overwrite the file with the sample
+    pub sampled: bool,
+    pub history: History,
+    // the constraints checked when the file is loaded and when the table is modified
+    pub schema: Option<Schema>,
+    // the lines of the file that do not have as many values as the header
+    pub bad_rows: Vec<BadRow>,
+    // the lines before the header skipped by --skip-lines
+    pub preamble: Vec<String>,
+}
+
+impl Table {
+    pub fn load(
+        path: &str,
+        alias: &str,
+        dialect: Dialect,
+        schema: Option<Schema>,
+    ) -> Option<Self> {
+        let options = OPTIONS.get().unwrap();
+        let file = csv_parser::parse_file(path, &dialect, schema.as_ref(), options)?;
+        let sampled = options.contains_key(&Options::Sample);
+        if sampled {
+            log_warning!(
+                "only a sample of {} is loaded, its changes can only be saved with save as",
+                path
+            );
+        }
+        Some(Table {
+            alias: alias.to_string(),
+            path: path.to_string(),
+            dialect,
+            fields: file.fields,
+            rows: file.rows,
+            saved_version: 0,
+            sampled,
+            history: History::default(),
+            schema,
+            bad_rows: file.bad_rows,
+            preamble: file.preamble,
+        })
+    }
+
+    // the default name of a table is the name of its file without the extension
+    pub fn alias_from_path(path: &str) -> String {
+        Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace('.', "_"))
+            .unwrap_or_default()
+    }
+
+    // the table has changes that are not written to its file
+    pub fn is_modified(&self) -> bool {
+        self.history.version() != self.saved_version
+    }
+
+    pub fn unsaved_changes(&self) -> usize {
+        self.history.changes_since(self.saved_version)
+    }
+
+    // the name of the file of the table, used in the prompt
+    pub fn file_name(&self) -> String {
+        Path::new(&self.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    fn has_kept_rows(&self) -> bool {
+        self.bad_rows
+            .iter()
+            .any(|bad_row| bad_row.action == BadRowAction::KeptRaw)
+    }
+
+    pub fn save(&mut self) -> bool {
+        if !csv_parser::write_file(
+            &self.path,
+            &self.dialect,
+            &self.fields,
+            &self.rows,
+            &self.bad_rows,
+            &self.preamble,
+            OPTIONS.get().unwrap(),
+        ) {
+            return false;
+        }
+        self.saved_version = self.history.version();
+        log_info!("the changes has been written to: {}", self.path);
+        true
+    }
+
+    // writes the table to an other file that becomes the file of the table
+    pub fn save_as(&mut self, path: &str) -> bool {
+        if self.sampled && path == self.path {
+            log_error!("only a sample of the file is loaded, it can not be written over the file");
+            return false;
+        }
+        // the new file is compressed as its extension tells (ex: data.csv.gz)
+        let dialect = Dialect {
+            compression: Compression::from_path(path),
+            ..self.dialect.clone()
+        };
+        if !csv_parser::write_file(
+            path,
+            &dialect,
+            &self.fields,
+            &self.rows,
+            &self.bad_rows,
+            &self.preamble,
+            OPTIONS.get().unwrap(),
+        ) {
+            return false;
+        }
+        self.dialect = dialect;
+        self.path = path.to_string();
+        self.saved_version = self.history.version();
+        // the new file holds exactly the loaded rows so it can be saved again
+        self.sampled = false;
+        log_info!("the table has been written to: {}", self.path);
+        true
+    }
+
+    // writes a copy of the table, the table keeps its file and its unsaved changes
+    pub fn export(&self, path: &str, dialect: &Dialect) -> bool {
+        // the kept lines use the separator of the file, they are only written back to it
+        if self.has_kept_rows() {
+            log_warning!("the lines kept by --on-bad-row=keep-raw are not exported");
+        }
+        if !csv_parser::write_file(
+            path,
+            dialect,
+            &self.fields,
+            &self.rows,
+            &[],
+            &[],
+            OPTIONS.get().unwrap(),
+        ) {
+            return false;
+        }
+        log_info!("the table has been exported to: {}", path);
+        true
+    }
+
+    // asks the user if the unsaved changes should be written to the file, returns false if the
+    // changes could not be saved
+    pub fn offer_to_save(&mut self) -> bool {
+        if !self.is_modified() {
+            return true;
+        }
+        if self.sampled {
+            // the sample can not be written over the file, only to an other one
+            log_warning!(
+                "only a sample of {} is loaded, its changes can not be saved to {}",
+                self.alias,
+                self.path
+            );
+            let question = "save the changes to an other file? (a path or nothing to discard them): ";
+            return match csv_parser::ask(question).as_deref().map(str::trim) {
+                Some("") | None => true,
+                Some(path) => self.save_as(unquote(path).unwrap_or(path)),
+            };
+        }
+        loop {
+            let question = format!(
+                "do you want to save the changes of {} to {}? (y or n): ",
+                self.alias, self.path
+            );
+            match csv_parser::ask(&question).as_deref() {
+                Some("y") => return self.save(),
+                Some("n") | None => return true,
+                Some(_) => continue,
+            }
+        }
+    }
+}
+
+// the state of the tables when a transaction, a savepoint or a script started
+pub struct Snapshot {
+    name: Option<String>,
+    tables: Vec<Table>,
+    current: usize,
+    // the number of savepoints that were open, restoring the snapshot closes the newer ones
+    depth: usize,
+}
+
+pub struct Session {
+    tables: Vec<Table>,
+    current: usize,
+    // the savepoints of the open transaction, the first one is created by the begin command
+    savepoints: Vec<Snapshot>,
+    // the number of scripts being run (a script can source an other one)
+    scripts: usize,
+}
+
+impl Session {
+    pub fn new(table: Table) -> Self {
+        Session {
+            tables: vec![table],
+            current: 0,
+            savepoints: Vec::new(),
+            scripts: 0,
+        }
+    }
+
+    pub fn current(&self) -> &Table {
+        &self.tables[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Table {
+        &mut self.tables[self.current]
+    }
+
+    pub fn get(&self, alias: &str) -> Option<&Table> {
+        self.tables.iter().find(|table| table.alias == alias)
+    }
+
+    pub fn tables_mut(&mut self) -> impl Iterator<Item = &mut Table> {
+        self.tables.iter_mut()
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        !self.savepoints.is_empty()
+    }
+
+    // a script is rolled back when one of its lines fails, its changes are not applied until it ends
+    pub fn enter_script(&mut self) {
+        self.scripts += 1;
+    }
+
+    pub fn leave_script(&mut self) {
+        self.scripts = self.scripts.saturating_sub(1);
+    }
+
+    // a written file can not be rolled back, so the files are not written in a transaction or a
+    // script (ex: action = saving)
+    fn can_write(&self, action: &str) -> bool {
+        if self.in_transaction() {
+            log_error!("commit or roll back the transaction before {}", action);
+            return false;
+        }
+        if self.scripts > 0 {
+            log_error!("{} is not possible in a script, it is rolled back when a line fails", action);
+            return false;
+        }
+        true
+    }
+
+    // runs the commands that manage the loaded tables and the transactions, returns None if the
+    // command is not one of them so it can be handled by the query engine, else if it succeeded
+    pub fn command(&mut self, command: &str) -> Option<bool> {
+        let lexemes: Vec<&str> = command.split(' ').filter(|l| !l.is_empty()).collect();
+        let succeeded = match lexemes.as_slice() {
+            ["open", args @ ..] => self.open(args),
+            ["use", alias] => self.use_table(alias),
+            ["use", ..] => {
+                log_error!("usage: use <table name>");
+                false
+            }
+            ["tables"] => {
+                self.print_tables();
+                true
+            }
+            ["close"] => {
+                let alias = self.current().alias.clone();
+                self.close(&alias)
+            }
+            ["close", alias] => self.close(alias),
+            ["undo"] => self.undo(),
+            ["redo"] => self.redo(),
+            ["bad-rows"] => {
+                self.print_bad_rows();
+                true
+            }
+            ["history"] => {
+                self.print_history();
+                true
+            }
+            ["save"] => self.save(),
+            ["save", "as", path] => match unquote(path) {
+                // the new file could not be removed by a rollback
+                Some(_) if !self.can_write("saving") => false,
+                Some(path) if !path.is_empty() => self.current_mut().save_as(path),
+                _ => {
+                    log_error!("usage: save as \"file.csv\"");
+                    false
+                }
+            },
+            ["export", args @ ..] => self.export(args),
+            ["begin"] => self.begin(),
+            ["commit"] => self.commit(),
+            ["rollback"] => self.rollback(None),
+            ["rollback", "to", name] => self.rollback(Some(name)),
+            ["savepoint", name] => self.savepoint(name),
+            ["rollback", ..] | ["savepoint", ..] => {
+                log_error!("usage: savepoint <name> and rollback (to <name>)?");
+                false
+            }
+            _ => return None,
+        };
+        Some(succeeded)
+    }
+
+    // the state of the tables, used to cancel the changes of a script that failed
+    pub fn checkpoint(&self) -> Snapshot {
+        Snapshot {
+            name: None,
+            tables: self.tables.clone(),
+            current: self.current,
+            depth: self.savepoints.len(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.tables = snapshot.tables;
+        self.current = snapshot.current;
+        self.savepoints.truncate(snapshot.depth);
+    }
+
+    fn begin(&mut self) -> bool {
+        if self.in_transaction() {
+            log_error!("a transaction is already open, use commit or rollback to end it");
+            return false;
+        }
+        self.savepoints.push(self.checkpoint());
+        log_info!("the transaction has started");
+        true
+    }
+
+    fn savepoint(&mut self, name: &str) -> bool {
+        if !self.in_transaction() {
+            log_error!("a savepoint can only be created inside a transaction, use begin first");
+            return false;
+        }
+        let mut snapshot = self.checkpoint();
+        snapshot.name = Some(name.to_string());
+        self.savepoints.push(snapshot);
+        true
+    }
+
+    pub fn commit(&mut self) -> bool {
+        if !self.in_transaction() {
+            log_error!("there is no transaction to commit");
+            return false;
+        }
+        self.savepoints.clear();
+        log_info!("the transaction has been committed");
+        true
+    }
+
+    // cancels the changes of the transaction, or only the changes made after a savepoint
+    pub fn rollback(&mut self, savepoint: Option<&str>) -> bool {
+        let idx = match savepoint {
+            None if self.in_transaction() => 0,
+            None => {
+                log_error!("there is no transaction to roll back");
+                return false;
+            }
+            Some(name) => {
+                let idx = self
+                    .savepoints
+                    .iter()
+                    .rposition(|snapshot| snapshot.name.as_deref() == Some(name));
+                match idx {
+                    Some(idx) => idx,
+                    None => {
+                        log_error!("no savepoint named {}", name);
+                        return false;
+                    }
+                }
+            }
+        };
+        let snapshot = self.savepoints.remove(idx);
+        let name = snapshot.name.clone();
+        self.restore(snapshot);
+        match name {
+            // the savepoint is kept so the transaction can roll back to it again
+            Some(name) => {
+                let mut snapshot = self.checkpoint();
+                snapshot.name = Some(name.clone());
+                self.savepoints.push(snapshot);
+                log_info!("the changes made after the savepoint {} are rolled back", name);
+            }
+            None => {
+                log_info!("the transaction has been rolled back");
+            }
+        }
+        true
+    }
+
+    // open "path" (as alias)? (separator "sep")? (schema "schema.toml")?
+    fn open(&mut self, args: &[&str]) -> bool {
+        let usage =
+            "usage: open \"file.csv\" (as <table name>)? (separator \";\")? (schema \"schema.toml\")?";
+        let Some(path) = args.first().and_then(|path| unquote(path)) else {
+            log_error!("{}", usage);
+            return false;
+        };
+        let mut alias = Table::alias_from_path(path);
+        let mut separator: Option<String> = None;
+        let mut schema: Option<Schema> = None;
+        let mut i = 1;
+        while i < args.len() {
+            match (args[i], args.get(i + 1)) {
+                ("as", Some(name)) => alias = name.to_string(),
+                ("separator", Some(sep)) => match unquote(sep).and_then(csv_parser::unescape) {
+                    Some(sep) => separator = Some(sep),
+                    _ => {
+                        log_error!("the separator should be between quotes (ex: separator \";\")");
+                        return false;
+                    }
+                },
+                ("schema", Some(schema_path)) => match unquote(schema_path).map(Schema::load) {
+                    Some(Some(loaded)) => schema = Some(loaded),
+                    Some(None) => return false,
+                    None => {
+                        log_error!("the schema path should be between quotes");
+                        return false;
+                    }
+                },
+                _ => {
+                    log_error!("{}", usage);
+                    return false;
+                }
+            }
+            i += 2;
+        }
+        if alias.is_empty() || alias.contains('.') {
+            log_error!("the table name '{}' should not be empty or contain dots", alias);
+            return false;
+        }
+        if self.get(&alias).is_some() {
+            log_error!("a table named {} is already open", alias);
+            return false;
+        }
+        let dialect = Dialect::of_file(path, separator.as_deref());
+        let Some(table) = Table::load(path, &alias, dialect, schema) else {
+            return false;
+        };
+        self.tables.push(table);
+        self.current = self.tables.len() - 1;
+        log_info!("{} is open as {}", path, alias);
+        true
+    }
+
+    fn use_table(&mut self, alias: &str) -> bool {
+        match self.tables.iter().position(|table| table.alias == alias) {
+            Some(idx) => self.current = idx,
+            None => {
+                log_error!("no table named {}", alias);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn close(&mut self, alias: &str) -> bool {
+        let Some(idx) = self.tables.iter().position(|table| table.alias == alias) else {
+            log_error!("no table named {}", alias);
+            return false;
+        };
+        if self.tables.len() == 1 {
+            log_error!("{} is the only open table, use quit to exit", alias);
+            return false;
+        }
+        // the table could be saved while closing it
+        if !self.can_write("closing a table") {
+            return false;
+        }
+        if !self.tables[idx].offer_to_save() {
+            return false;
+        }
+        self.tables.remove(idx);
+        if self.current >= idx && self.current > 0 {
+            self.current -= 1;
+        }
+        log_info!("{} is closed, the current table is {}", alias, self.current().alias);
+        true
+    }
+
+    fn save(&mut self) -> bool {
+        if self.current().sampled {
+            log_error!("only a sample of the file is loaded, use save as to write it to an other file");
+            return false;
+        }
+        if !self.can_write("saving") {
+            return false;
+        }
+        self.current_mut().save()
+    }
+
+    // export "path" (separator "sep")?
+    fn export(&self, args: &[&str]) -> bool {
+        let usage = "usage: export \"file.csv\" (separator \";\")? (--compress=<gzip|zstd|none>)?";
+        let mut dialect = self.current().dialect.clone();
+        // the copy is written with the separator of export, --output-separator or the file
+        dialect.output_separator = OPTIONS.get().unwrap().get(&Options::OutputSeparator).cloned();
+        // the copy is compressed as its extension tells unless --compress is given
+        let mut compression: Option<Compression> = None;
+        let mut args = args;
+        if let [rest @ .., last] = args
+            && let Some(name) = last.strip_prefix("--compress=")
+        {
+            let Some(format) = Compression::from_name(name) else {
+                log_error!("{}", usage);
+                return false;
+            };
+            compression = Some(format);
+            args = rest;
+        }
+        let path = match args {
+            [path] => unquote(path),
+            [path, "separator", separator] => match unquote(separator).and_then(csv_parser::unescape) {
+                Some(separator) => {
+                    dialect.output_separator = Some(separator);
+                    unquote(path)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        match path {
+            Some(path) if !path.is_empty() => {
+                dialect.compression = compression.unwrap_or(Compression::from_path(path));
+                self.current().export(path, &dialect)
+            }
+            _ => {
+                log_error!("{}", usage);
+                false
+            }
+        }
+    }
+
+    // reverts the last change of the current table
+    fn undo(&mut self) -> bool {
+        let table = self.current_mut();
+        match table.history.undo(&mut table.fields, &mut table.rows) {
+            Some(command) => {
+                log_info!("undone: {}", command);
+                true
+            }
+            None => {
+                log_error!("there is nothing to undo");
+                false
+            }
+        }
+    }
+
+    fn redo(&mut self) -> bool {
+        let table = self.current_mut();
+        match table.history.redo(&mut table.fields, &mut table.rows) {
+            Some(command) => {
+                log_info!("redone: {}", command);
+                true
+            }
+            None => {
+                log_error!("there is nothing to redo");
+                false
+            }
+        }
+    }
+
+    // lists the lines of the current table that did not have as many values as the header
+    fn print_bad_rows(&self) {
+        let table = self.current();
+        if table.bad_rows.is_empty() {
+            log_info!("every line of {} has {} values", table.path, table.fields.len());
+            return;
+        }
+        for bad_row in table.bad_rows.iter() {
+            let action = match bad_row.action {
+                BadRowAction::Skipped => "skipped",
+                BadRowAction::Padded => "padded",
+                BadRowAction::KeptRaw => "kept raw",
+            };
+            println!(
+                "line {} ({} values, {}): {}",
+                bad_row.line, bad_row.values, action, bad_row.raw
+            );
+        }
+    }
+
+    // lists the commands of the current table that can be undone, the last one is undone first
+    fn print_history(&self) {
+        if self.current().history.commands().next().is_none() {
+            log_info!("there is no change to undo");
+            return;
+        }
+        println!();
+        for (i, command) in self.current().history.commands().enumerate() {
+            println!("{} {}", i + 1, command);
+        }
+    }
+
+    fn print_tables(&self) {
+        println!();
+        for (i, table) in self.tables.iter().enumerate() {
+            println!(
+                "{} {}{} ({}, {} fields, {} rows)",
+                if i == self.current { ">" } else { " " },
+                table.alias,
+                if table.is_modified() { "*" } else { "" },
+                table.path,
+                table.fields.len(),
+                table.rows.len()
+            );
+        }
+    }
+}
+
+// removes the quotes around a literal
+fn unquote(lexeme: &str) -> Option<&str> {
+    lexeme.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_parser::BadRowPolicy;
+    use crate::history::Change;
+
+    fn get_session() -> Session {
+        Session::new(Table {
+            alias: "t".to_string(),
+            path: "t.csv".to_string(),
+            dialect: Dialect {
+                separator: ",".to_string(),
+                quote: Some('"'),
+                on_bad_row: BadRowPolicy::Skip,
+                has_header: true,
+                names: None,
+                skip_lines: 0,
+                output_separator: None,
+                encoding: encoding_rs::UTF_8,
+                lossy: false,
+                byte_order_mark: false,
+                crlf: false,
+                compression: Compression::None,
+            },
+            fields: vec!["id".to_string()],
+            rows: vec![vec!["1".to_string()]],
+            saved_version: 0,
+            sampled: false,
+            history: History::default(),
+            schema: None,
+            bad_rows: Vec::new(),
+            preamble: Vec::new(),
+        })
+    }
+
+    fn push_row(session: &mut Session, val: &str) {
+        let table = session.current_mut();
+        let change = Change::AddedRows(vec![vec![val.to_string()]]);
+        change.redo(&mut table.fields, &mut table.rows);
+        table.history.record("insert-row", change);
+    }
+
+    #[test]
+    fn sample_is_not_written_over_its_file() {
+        let mut session = get_session();
+        session.current_mut().sampled = true;
+        assert!(!session.current_mut().save_as("t.csv"));
+        assert_eq!(session.command("save"), Some(false));
+    }
+
+    #[test]
+    fn rollback_transaction() {
+        let mut session = get_session();
+        assert_eq!(session.command("rollback"), Some(false));
+        assert_eq!(session.command("begin"), Some(true));
+        assert_eq!(session.command("begin"), Some(false));
+        push_row(&mut session, "2");
+        assert_eq!(session.command("rollback"), Some(true));
+        assert_eq!(session.current().rows.len(), 1);
+        assert!(!session.current().is_modified());
+        assert!(!session.in_transaction());
+    }
+
+    #[test]
+    fn rollback_to_savepoint() {
+        let mut session = get_session();
+        assert_eq!(session.command("savepoint a"), Some(false));
+        session.command("begin");
+        push_row(&mut session, "2");
+        assert_eq!(session.command("savepoint a"), Some(true));
+        push_row(&mut session, "3");
+        assert_eq!(session.command("rollback to a"), Some(true));
+        assert_eq!(session.current().rows.len(), 2);
+        // the savepoint is still there after rolling back to it
+        push_row(&mut session, "4");
+        assert_eq!(session.command("rollback to a"), Some(true));
+        assert_eq!(session.current().rows.len(), 2);
+        assert_eq!(session.command("rollback to b"), Some(false));
+        assert_eq!(session.command("commit"), Some(true));
+        assert_eq!(session.current().rows.len(), 2);
+        assert_eq!(session.command("commit"), Some(false));
+    }
+
+    #[test]
+    fn restore_checkpoint() {
+        let mut session = get_session();
+        let checkpoint = session.checkpoint();
+        session.command("begin");
+        push_row(&mut session, "2");
+        session.restore(checkpoint);
+        assert_eq!(session.current().rows.len(), 1);
+        // the transaction started after the checkpoint is closed
+        assert!(!session.in_transaction());
+    }
+}
@@ -0,0 +1,196 @@
+// guesses how a csv file is written from its first lines: the separator, the quote character and
+// if the first line is a header
+use crate::compression;
+use crate::csv_parser;
+use crate::schema::Kind;
+use encoding_rs::Encoding;
+use std::collections::HashMap;
+use std::io::Read;
+
+// the number of bytes read from the start of the file
+const SAMPLE_SIZE: usize = 16 * 1024;
+const SEPARATORS: [&str; 4] = [",", ";", "\t", "|"];
+const QUOTES: [char; 2] = ['"', '\''];
+
+#[derive(Debug, PartialEq)]
+pub struct Guess {
+    pub separator: String,
+    pub quote: Option<char>,
+    pub has_header: bool,
+}
+
+pub struct Sample {
+    // the non empty lines at the start of the file after the skipped lines
+    pub lines: Vec<String>,
+    // the encoding given by the byte order mark at the start of the file
+    pub byte_order_mark: Option<&'static Encoding>,
+    // the lines end with \r\n
+    pub crlf: bool,
+}
+
+// the start of the file decoded with the encoding, None if it can not be read
+pub fn sample(path: &str, skip_lines: usize, encoding: &'static Encoding) -> Option<Sample> {
+    let mut bytes: Vec<u8> = Vec::new();
+    compression::open(path)
+        .ok()?
+        .take(SAMPLE_SIZE as u64)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    let byte_order_mark = Encoding::for_bom(&bytes).map(|(encoding, _)| encoding);
+    // the byte order mark is removed and used instead of the encoding
+    let (content, _, _) = encoding.decode(&bytes);
+    let crlf = content
+        .find('\n')
+        .is_some_and(|end| content[..end].ends_with('\r'));
+    let mut lines: Vec<String> = content.lines().skip(skip_lines).map(String::from).collect();
+    // the last line of a full sample is probably cut
+    if bytes.len() == SAMPLE_SIZE && lines.len() > 1 {
+        lines.pop();
+    }
+    lines.retain(|line| !line.trim().is_empty());
+    Some(Sample {
+        lines,
+        byte_order_mark,
+        crlf,
+    })
+}
+
+// the given separator and quote are kept, the quote is Some(None) when the values are not quoted
+pub fn sniff(lines: &[String], separator: Option<&str>, quote: Option<Option<char>>) -> Guess {
+    let separator = match separator {
+        Some(separator) => separator.to_string(),
+        None => guess_separator(lines, quote.unwrap_or(Some('"'))),
+    };
+    let quote = quote.unwrap_or_else(|| guess_quote(lines, &separator));
+    let rows: Vec<Vec<String>> = lines
+        .iter()
+        .map(|line| csv_parser::split_line(line, &separator, quote))
+        .collect();
+    Guess {
+        has_header: guess_header(&rows),
+        separator,
+        quote,
+    }
+}
+
+// the separator that splits the most lines into the same number of values, a file of one column is
+// separated by commas
+fn guess_separator(lines: &[String], quote: Option<char>) -> String {
+    let mut best = (csv_parser::DEFAULT_SEPARATOR, 0, 0);
+    for separator in SEPARATORS {
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for line in lines {
+            *counts
+                .entry(csv_parser::split_line(line, separator, quote).len())
+                .or_default() += 1;
+        }
+        // the most frequent number of values and the number of lines that have it
+        let Some((values, lines)) = counts
+            .into_iter()
+            .filter(|(values, _)| *values > 1)
+            .max_by_key(|(values, lines)| (*lines, *values))
+        else {
+            continue;
+        };
+        if (lines, values) > (best.1, best.2) {
+            best = (separator, lines, values);
+        }
+    }
+    best.0.to_string()
+}
+
+// the quote that surrounds the most values, the double quote if no value is quoted
+fn guess_quote(lines: &[String], separator: &str) -> Option<char> {
+    let quoted = |quote: char| {
+        lines
+            .iter()
+            .flat_map(|line| line.split(separator))
+            .map(|value| value.trim())
+            .filter(|value| value.len() > 1 && value.starts_with(quote) && value.ends_with(quote))
+            .count()
+    };
+    let (quote, count) = QUOTES
+        .into_iter()
+        .map(|quote| (quote, quoted(quote)))
+        .max_by_key(|(_, count)| *count)?;
+    match count {
+        0 => Some('"'),
+        _ => Some(quote),
+    }
+}
+
+// every column votes: the first line is a header when its value is not a number while the other
+// values are, or when its length differs from the values that all have the same length, a file
+// where no column votes keeps its header
+fn guess_header(rows: &[Vec<String>]) -> bool {
+    let Some((first, rest)) = rows.split_first() else {
+        return true;
+    };
+    let mut votes = 0;
+    for (i, name) in first.iter().enumerate() {
+        let values: Vec<&str> = rest
+            .iter()
+            .filter_map(|row| row.get(i))
+            .map(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        if values.iter().all(|value| Kind::Number.parses(value)) {
+            votes += if Kind::Number.parses(name) { -1 } else { 1 };
+            continue;
+        }
+        let length = values[0].chars().count();
+        if values.iter().all(|value| value.chars().count() == length) {
+            votes += if name.chars().count() == length { -1 } else { 1 };
+        }
+    }
+    votes >= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(content: &str) -> Vec<String> {
+        content.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn guess_the_separator_and_the_quote() {
+        let guess = sniff(&lines("id;name\n1;\"b;ob\"\n2;ann\n"), None, None);
+        assert_eq!(guess.separator, ";");
+        assert_eq!(guess.quote, Some('"'));
+        assert!(guess.has_header);
+
+        let guess = sniff(&lines("1\t'a'\t3\n2\t'b'\t4\n"), None, None);
+        assert_eq!(guess.separator, "\t");
+        assert_eq!(guess.quote, Some('\''));
+        assert!(!guess.has_header);
+
+        let guess = sniff(&lines("name\nbob\n"), None, None);
+        assert_eq!(guess.separator, ",");
+    }
+
+    #[test]
+    fn given_settings_are_kept() {
+        let guess = sniff(&lines("a|b;c\n1|2;3\n"), Some(";"), Some(None));
+        assert_eq!(guess.separator, ";");
+        assert_eq!(guess.quote, None);
+    }
+
+    #[test]
+    fn guess_the_header() {
+        let rows = |content: &str| -> Vec<Vec<String>> {
+            lines(content)
+                .iter()
+                .map(|line| csv_parser::split_line(line, ",", None))
+                .collect()
+        };
+        assert!(guess_header(&rows("id,name\n1,bob\n2,jack\n")));
+        assert!(!guess_header(&rows("1,bob\n2,jack\n3,ann\n")));
+        assert!(guess_header(&rows("code,city\nAB,paris\nCD,rome\n")));
+        assert!(!guess_header(&rows("AB,rome\nCD,nice\n")));
+    }
+}
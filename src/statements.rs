@@ -0,0 +1,113 @@
+// splits the input into statements: a statement ends with a ; or with the end of a line when
+// nothing is left open, so a parenthesis, a bracket or a quote that is not closed continues the
+// statement on the next line
+#[derive(Debug, Default)]
+pub struct Statements {
+    // the text of the statement that is not finished yet
+    pending: String,
+    depth: usize,
+    in_quote: bool,
+}
+
+impl Statements {
+    // adds a line of input, returns the statements it finished
+    pub fn push_line(&mut self, line: &str) -> Vec<String> {
+        // the spaces of a line that continues a quote are part of the string
+        let line = if self.in_quote { line } else { line.trim_start() };
+        if line.is_empty() && !self.in_quote {
+            return Vec::new();
+        }
+        // the lines of a statement are joined with a space, the lexemes are split on single spaces,
+        // the lines of a quote are joined without the line break since a saved value can not hold one
+        if !self.pending.is_empty() && !self.pending.ends_with(' ') && !self.in_quote {
+            self.pending.push(' ');
+        }
+
+        let mut statements: Vec<String> = Vec::new();
+        for c in line.chars() {
+            match c {
+                '"' => self.in_quote = !self.in_quote,
+                '(' | '[' if !self.in_quote => self.depth += 1,
+                ')' | ']' if !self.in_quote => self.depth = self.depth.saturating_sub(1),
+                ';' if !self.in_quote && self.depth == 0 => {
+                    statements.extend(self.take());
+                    continue;
+                }
+                _ => {}
+            }
+            self.pending.push(c);
+        }
+        // the spaces at the end of the line are only kept in a quote
+        if !self.in_quote {
+            self.pending.truncate(self.pending.trim_end().len());
+        }
+        if !self.is_open() {
+            statements.extend(self.take());
+        }
+        statements
+    }
+
+    // a statement was started and it is waiting for the next lines
+    pub fn is_open(&self) -> bool {
+        self.depth > 0 || self.in_quote
+    }
+
+    // drops the unfinished statement (ex: ctrl-c at a continuation prompt)
+    pub fn clear(&mut self) {
+        *self = Statements::default();
+    }
+
+    fn take(&mut self) -> Option<String> {
+        let statement = self.pending.trim().to_string();
+        self.clear();
+        if statement.is_empty() {
+            None
+        } else {
+            Some(statement)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn several_statements_on_one_line() {
+        let mut statements = Statements::default();
+        assert_eq!(
+            statements.push_line("begin; delete where $id == 2 ;commit;"),
+            vec!["begin", "delete where $id == 2", "commit"]
+        );
+        assert_eq!(statements.push_line("get *"), vec!["get *"]);
+    }
+
+    #[test]
+    fn open_statements_continue() {
+        let mut statements = Statements::default();
+        assert!(statements.push_line("get * where ( $age > 30").is_empty());
+        assert!(statements.is_open());
+        assert_eq!(
+            statements.push_line("  or $name is \"a;b\" )"),
+            vec!["get * where ( $age > 30 or $name is \"a;b\" )"]
+        );
+        assert!(!statements.is_open());
+
+        assert!(statements.push_line("set $name = \"a").is_empty());
+        assert_eq!(statements.push_line("b\";"), vec!["set $name = \"ab\""]);
+        assert!(statements.push_line("set $name = \"a ").is_empty());
+        assert!(statements.push_line("").is_empty());
+        assert_eq!(statements.push_line("  b\""), vec!["set $name = \"a   b\""]);
+    }
+
+    #[test]
+    fn separators_in_quotes_and_lists() {
+        let mut statements = Statements::default();
+        assert_eq!(
+            statements.push_line("get * where $name is \"x;y\"; get $id"),
+            vec!["get * where $name is \"x;y\"", "get $id"]
+        );
+        assert!(statements.push_line("insert-row $id = [1;").is_empty());
+        assert_eq!(statements.push_line("2]"), vec!["insert-row $id = [1; 2]"]);
+    }
+}